@@ -17,6 +17,7 @@ pub struct AnimatedSpriteBundle {
 }
 
 #[derive(Debug, Component)]
+#[allow(dead_code)]
 struct SpriteAnimation {
     speed: f32,
     accumulator: f32,
@@ -25,6 +26,7 @@ struct SpriteAnimation {
     transform: Transform,
 }
 
+#[allow(dead_code)]
 impl SpriteAnimation {
     pub fn new(range: Range<u32>, speed: f32, transform: Transform) -> Self {
         Self {
@@ -0,0 +1,165 @@
+use crate::{
+    map::{transition::LevelTransition, LevelScale},
+    player::Player,
+};
+use avian2d::prelude::*;
+use bevy::{prelude::*, transform::TransformSystem};
+
+/// Moves every [`GameCamera`] together, either locked to the level or following the player.
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CameraMode>()
+            .init_resource::<CameraRig>()
+            .add_systems(
+                PostUpdate,
+                move_cameras
+                    .after(PhysicsSet::Sync)
+                    .before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+/// Marks the gameplay, ghost and background cameras, which always share a view.
+#[derive(Component, Default)]
+pub struct GameCamera;
+
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CameraMode {
+    /// Shows the whole level, panning to the next one on a level change.
+    #[default]
+    Locked,
+    /// Zooms in and trails the player inside the level.
+    Follow,
+}
+
+impl CameraMode {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Locked => Self::Follow,
+            Self::Follow => Self::Locked,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Locked => "Camera: Locked",
+            Self::Follow => "Camera: Follow",
+        }
+    }
+}
+
+/// Projection scale while following, below 1 zooms in.
+const FOLLOW_ZOOM: f32 = 0.5;
+/// How far the player may move from the focus before dragging it along, in world units.
+const FOLLOW_DEADZONE: Vec2 = Vec2::new(96., 64.);
+/// Seconds of horizontal velocity the camera leads the player by.
+const LOOK_AHEAD_SECONDS: f32 = 0.3;
+const LOOK_AHEAD_MAX: f32 = 160.;
+/// Exponential rates, higher is snappier.
+const PAN_RATE: f32 = 10.;
+const FOLLOW_RATE: f32 = 6.;
+const LOOK_AHEAD_RATE: f32 = 3.;
+const ZOOM_RATE: f32 = 8.;
+
+/// Where the cameras are and what they are chasing, in world units.
+#[derive(Resource, Debug, Default)]
+pub struct CameraRig {
+    position: Vec2,
+    focus: Vec2,
+    look_ahead: f32,
+    zoom: f32,
+    /// The player's position last frame, or `None` if there was no player to follow.
+    last_player: Option<Vec2>,
+}
+
+pub fn move_cameras(
+    mut cameras: Query<(&mut Transform, &mut OrthographicProjection), With<GameCamera>>,
+    player: Query<(&Transform, &LinearVelocity), (With<Player>, Without<GameCamera>)>,
+    mut transitions: EventReader<LevelTransition>,
+    mut rig: ResMut<CameraRig>,
+    mode: Res<CameraMode>,
+    level_scale: Res<LevelScale>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_seconds();
+    let changed_level = transitions.read().count() > 0;
+
+    // The player is worldly and the scaled world is centred on the origin.
+    let player = player.get_single().ok().map(|(transform, velocity)| {
+        let position =
+            (transform.translation.truncate() - level_scale.level_size / 2.) * level_scale.scale;
+        (position, velocity.0)
+    });
+
+    let zoom = match *mode {
+        CameraMode::Locked => 1.,
+        CameraMode::Follow => FOLLOW_ZOOM,
+    };
+    let snap = rig.last_player.is_none() || rig.zoom == 0.;
+
+    if let Some((position, velocity)) = player {
+        // The levels are spawned on top of each other, so keep the view where it was
+        // relative to the player and pan over to the new level from there.
+        match rig.last_player {
+            None => {
+                rig.focus = position;
+                rig.look_ahead = 0.;
+            }
+            Some(last) if changed_level => {
+                let shift = position - last;
+                rig.position += shift;
+                rig.focus += shift;
+            }
+            Some(_) => {}
+        }
+
+        let offset = position - rig.focus;
+        rig.focus += offset - offset.clamp(-FOLLOW_DEADZONE, FOLLOW_DEADZONE);
+
+        let look_ahead = (velocity.x * LOOK_AHEAD_SECONDS).clamp(-LOOK_AHEAD_MAX, LOOK_AHEAD_MAX);
+        rig.look_ahead += (look_ahead - rig.look_ahead) * approach(LOOK_AHEAD_RATE, dt);
+    }
+    rig.last_player = player.map(|(position, _)| position);
+
+    // Keep the view inside the level, unless the level is smaller than the view.
+    let Some(half_view) = cameras
+        .iter()
+        .next()
+        .map(|(_, projection)| projection.area.half_size() / projection.scale * zoom)
+    else {
+        return;
+    };
+    let half_level = level_scale.level_size * level_scale.scale / 2.;
+    let bounds = (half_level - half_view).max(Vec2::ZERO);
+
+    let (target, rate) = match *mode {
+        CameraMode::Locked => (Vec2::ZERO, PAN_RATE),
+        CameraMode::Follow => (
+            (rig.focus + Vec2::X * rig.look_ahead).clamp(-bounds, bounds),
+            FOLLOW_RATE,
+        ),
+    };
+
+    if snap {
+        rig.position = target;
+        rig.zoom = zoom;
+    } else {
+        rig.position = rig.position.lerp(target, approach(rate, dt));
+        rig.zoom += (zoom - rig.zoom) * approach(ZOOM_RATE, dt);
+    }
+
+    for (mut transform, mut projection) in &mut cameras {
+        transform.translation.x = rig.position.x;
+        transform.translation.y = rig.position.y;
+        if projection.scale != rig.zoom {
+            projection.scale = rig.zoom;
+        }
+    }
+}
+
+/// Frame rate independent interpolation factor for an exponential approach.
+fn approach(rate: f32, dt: f32) -> f32 {
+    1. - (-rate * dt).exp()
+}
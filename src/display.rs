@@ -0,0 +1,138 @@
+use crate::{camera::GameCamera, map::VIEW_SIZE};
+use bevy::{
+    prelude::*,
+    render::{
+        camera::{RenderTarget, ScalingMode},
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
+        view::RenderLayers,
+    },
+    window::{PrimaryWindow, WindowMode, WindowResolution},
+};
+
+/// Side of the square the game is rendered at, in pixels, before being scaled up to
+/// the window. One pixel per world unit, so no detail of the view is lost.
+pub const CANVAS_SIZE: u32 = VIEW_SIZE as u32;
+
+/// Holds the [`Canvas`] sprite, seen only by the window camera.
+pub const DISPLAY_LAYER: usize = 3;
+
+/// The initial window, the canvas size.
+pub fn window_resolution() -> WindowResolution {
+    WindowResolution::new(CANVAS_SIZE as f32, CANVAS_SIZE as f32)
+}
+
+/// Renders every [`GameCamera`] into a fixed size [`Canvas`] and shows it scaled by a
+/// whole number, with black bars filling the rest of the window.
+pub struct DisplayPlugin;
+
+impl Plugin for DisplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_canvas).add_systems(
+            Update,
+            (target_canvas, toggle_fullscreen, fit_canvas).chain(),
+        );
+    }
+}
+
+/// The image the game cameras draw into.
+#[derive(Resource)]
+pub struct Canvas(pub Handle<Image>);
+
+#[derive(Component)]
+struct CanvasSprite;
+
+fn setup_canvas(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    let size = Extent3d {
+        width: CANVAS_SIZE,
+        height: CANVAS_SIZE,
+        depth_or_array_layers: 1,
+    };
+
+    let mut image = Image::new_fill(
+        size,
+        TextureDimension::D2,
+        &[0, 0, 0, 0],
+        TextureFormat::Bgra8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    image.texture_descriptor.usage =
+        TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST | TextureUsages::RENDER_ATTACHMENT;
+    let canvas = images.add(image);
+
+    commands.spawn((
+        SpriteBundle {
+            texture: canvas.clone(),
+            ..Default::default()
+        },
+        CanvasSprite,
+        RenderLayers::layer(DISPLAY_LAYER),
+    ));
+    commands.spawn((
+        Camera2dBundle {
+            camera: Camera {
+                // After the game cameras have filled the canvas. Also the UI camera, so
+                // menus and overlays use the whole window at its native resolution.
+                order: 10,
+                clear_color: ClearColorConfig::Custom(Color::BLACK),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        RenderLayers::layer(DISPLAY_LAYER),
+    ));
+
+    commands.insert_resource(Canvas(canvas));
+}
+
+/// Points newly spawned game cameras at the canvas, showing the same area of the world
+/// whatever the window size.
+fn target_canvas(
+    mut cameras: Query<(&mut Camera, &mut OrthographicProjection), Added<GameCamera>>,
+    canvas: Res<Canvas>,
+) {
+    for (mut camera, mut projection) in &mut cameras {
+        camera.target = RenderTarget::Image(canvas.0.clone());
+        projection.scaling_mode = ScalingMode::Fixed {
+            width: VIEW_SIZE,
+            height: VIEW_SIZE,
+        };
+    }
+}
+
+fn toggle_fullscreen(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut window: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if !keys.just_pressed(KeyCode::F11) {
+        return;
+    }
+
+    if let Ok(mut window) = window.get_single_mut() {
+        window.mode = match window.mode {
+            WindowMode::Windowed => WindowMode::BorderlessFullscreen,
+            _ => WindowMode::Windowed,
+        };
+    }
+}
+
+/// Scales the canvas by the largest whole number of physical pixels that fits the
+/// window, or shrinks it to fit when the window is smaller than the canvas.
+fn fit_canvas(
+    window: Query<&Window, (With<PrimaryWindow>, Changed<Window>)>,
+    mut sprite: Query<&mut Transform, With<CanvasSprite>>,
+) {
+    let Ok(window) = window.get_single() else {
+        return;
+    };
+
+    let physical = window.physical_size().as_vec2();
+    let fit = (physical / CANVAS_SIZE as f32).min_element();
+    let factor = if fit >= 1. { fit.floor() } else { fit };
+
+    // The window camera works in logical pixels.
+    let scale = factor / window.scale_factor();
+    for mut transform in &mut sprite {
+        transform.scale = Vec3::new(scale, scale, 1.);
+    }
+}
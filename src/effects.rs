@@ -0,0 +1,664 @@
+use crate::{
+    map::{background::BACKGROUND_LAYER, GoalReached},
+    player::{
+        fall::PlayerFell,
+        jump_profile::JumpTuning,
+        movement::{Jumped, WallBounce},
+        JuiceMeter, Player,
+    },
+    state::GameState,
+};
+use avian2d::prelude::*;
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    render::view::RenderLayers,
+    utils::HashMap,
+};
+use bevy_ecs_ldtk::prelude::*;
+use bevy_hanabi::prelude::*;
+use ron::error::SpannedError;
+use serde::Deserialize;
+use std::{fmt, io};
+
+/// Builds the particle effects in `assets/particles.effects.ron` into an
+/// [`EffectRegistry`], places LDtk emitters and plays one-shot bursts by name.
+pub struct EffectsPlugin;
+
+impl Plugin for EffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(HanabiPlugin)
+            .register_ldtk_entity::<EmitterBundle>("Emitter")
+            .init_asset::<EffectLibrary>()
+            .init_asset_loader::<EffectLibraryLoader>()
+            .init_resource::<EffectRegistry>()
+            .init_resource::<PlayerParticles>()
+            .add_event::<PlayEffect>()
+            .add_systems(
+                Update,
+                (
+                    build_registry,
+                    (burst_on_gameplay_events, kick_up_dust, charge_sparks)
+                        .run_if(in_state(GameState::Playing)),
+                    spawn_emitters,
+                    play_effects,
+                    despawn_bursts,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Played under the player when a jump launches.
+pub const JUMP_EFFECT: &str = "jump";
+/// Played under the player when it lands after a fall.
+pub const LAND_EFFECT: &str = "land";
+/// Played under the player instead of [`LAND_EFFECT`] when a fall splats it.
+pub const LONG_FALL_EFFECT: &str = "long_fall";
+/// Played under the player over and over while charging a jump.
+pub const CHARGE_EFFECT: &str = "charge_spark";
+/// Played where the player bounces off a wall.
+pub const WALL_BOUNCE_EFFECT: &str = "wall_bounce";
+/// Played on the player when it reaches the goal.
+pub const GOAL_EFFECT: &str = "goal";
+/// Played where the player touches a hazard.
+pub const DEATH_EFFECT: &str = "death";
+/// Looped across every wind zone while its wind blows.
+pub const GUST_EFFECT: &str = "gust";
+
+/// Named effect definitions, loaded from a `.effects.ron` file.
+#[derive(Asset, TypePath, Deserialize, Debug, Clone)]
+pub struct EffectLibrary {
+    pub effects: HashMap<String, EffectDefinition>,
+}
+
+/// A particle effect. Distances are in LDtk pixels and times in seconds.
+#[derive(Deserialize, Debug, Clone)]
+pub struct EffectDefinition {
+    /// Most particles alive at once.
+    #[serde(default = "default_capacity")]
+    pub capacity: u32,
+    pub spawn: EffectSpawn,
+    #[serde(default)]
+    pub shape: EffectShape,
+    #[serde(default)]
+    pub velocity: EffectVelocity,
+    #[serde(default)]
+    pub acceleration: (f32, f32),
+    /// Lifetime of each particle, picked between the two.
+    pub lifetime: (f32, f32),
+    /// Size of each particle, picked between the two.
+    pub size: (f32, f32),
+    /// Height of each particle, picked between the two. Defaults to the size, for
+    /// square particles.
+    #[serde(default)]
+    pub height: Option<(f32, f32)>,
+    /// Keys of `(age, (red, green, blue, alpha))`, with ages from 0 to 1.
+    pub gradient: Vec<(f32, (f32, f32, f32, f32))>,
+    /// Stretches particles in the direction they move.
+    #[serde(default)]
+    pub along_velocity: bool,
+}
+
+fn default_capacity() -> u32 {
+    1024
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub enum EffectSpawn {
+    /// Particles per second.
+    Rate(f32),
+    /// A single burst, for one-shot effects.
+    Once(f32),
+    /// A burst of `count` every `period`.
+    Burst { count: f32, period: f32 },
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+pub enum EffectShape {
+    #[default]
+    Point,
+    Circle {
+        radius: f32,
+        /// Spawns on the edge instead of inside.
+        #[serde(default)]
+        edge: bool,
+    },
+    Rect {
+        width: f32,
+        height: f32,
+    },
+    /// Inside a rectangle centred on the effect and sized by its `extent` property, set
+    /// by whatever plays it, like a wind zone.
+    Extent,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub enum EffectVelocity {
+    /// Away from the emitter, with a speed picked between the two.
+    Radial { speed: (f32, f32) },
+    /// The same direction for every particle, give or take `jitter` on each axis.
+    Linear {
+        velocity: (f32, f32),
+        #[serde(default)]
+        jitter: (f32, f32),
+    },
+    /// The effect's `velocity` property, set by whatever plays it, like a wind zone.
+    Property,
+}
+
+impl Default for EffectVelocity {
+    fn default() -> Self {
+        Self::Linear {
+            velocity: (0., 0.),
+            jitter: (0., 0.),
+        }
+    }
+}
+
+impl EffectDefinition {
+    /// Longest a single spawn keeps particles alive.
+    pub fn duration(&self) -> f32 {
+        self.lifetime.0.max(self.lifetime.1)
+    }
+
+    pub fn build(&self, name: &str) -> EffectAsset {
+        let mut gradient = Gradient::new();
+        for &(age, (r, g, b, a)) in &self.gradient {
+            gradient.add_key(age, Vec4::new(r, g, b, a));
+        }
+
+        let mut module = Module::default();
+
+        let position = match self.shape {
+            EffectShape::Point => module.lit(Vec3::ZERO),
+            EffectShape::Circle { radius, edge } => {
+                let direction = random_direction(&mut module);
+                let distance = if edge {
+                    module.lit(radius)
+                } else {
+                    // The square root spreads particles evenly over the area.
+                    let random = module.builtin(BuiltInOperator::Rand(ScalarType::Float.into()));
+                    let random = module.sqrt(random);
+                    let radius = module.lit(radius);
+                    module.mul(random, radius)
+                };
+                module.mul(direction, distance)
+            }
+            EffectShape::Rect { width, height } => {
+                let random = module.builtin(BuiltInOperator::Rand(VectorType::VEC3F.into()));
+                let half = module.lit(Vec3::new(0.5, 0.5, 0.));
+                let offset = module.sub(random, half);
+                let extent = module.lit(Vec3::new(width, height, 0.));
+                module.mul(offset, extent)
+            }
+            EffectShape::Extent => {
+                let extent = module.add_property("extent", Vec3::ZERO.into());
+                let random = module.builtin(BuiltInOperator::Rand(VectorType::VEC3F.into()));
+                let half = module.lit(Vec3::new(0.5, 0.5, 0.));
+                let offset = module.sub(random, half);
+                let extent = module.prop(extent);
+                module.mul(offset, extent)
+            }
+        };
+        let init_pos = SetAttributeModifier::new(Attribute::POSITION, position);
+
+        let velocity = match self.velocity {
+            EffectVelocity::Radial { speed: (min, max) } => {
+                let direction = random_direction(&mut module);
+                let min = module.lit(min);
+                let max = module.lit(max);
+                let speed = module.uniform(min, max);
+                module.mul(direction, speed)
+            }
+            EffectVelocity::Linear {
+                velocity: (x, y),
+                jitter: (jitter_x, jitter_y),
+            } => {
+                let random = module.builtin(BuiltInOperator::Rand(VectorType::VEC3F.into()));
+                let half = module.lit(Vec3::new(0.5, 0.5, 0.));
+                let offset = module.sub(random, half);
+                let jitter = module.lit(Vec3::new(jitter_x * 2., jitter_y * 2., 0.));
+                let jitter = module.mul(offset, jitter);
+                let velocity = module.lit(Vec3::new(x, y, 0.));
+                module.add(velocity, jitter)
+            }
+            EffectVelocity::Property => {
+                let velocity = module.add_property("velocity", Vec3::ZERO.into());
+                module.prop(velocity)
+            }
+        };
+        let init_vel = SetAttributeModifier::new(Attribute::VELOCITY, velocity);
+
+        let min_lifetime = module.lit(self.lifetime.0);
+        let max_lifetime = module.lit(self.lifetime.1);
+        let lifetime = module.uniform(min_lifetime, max_lifetime);
+        let init_lifetime = SetAttributeModifier::new(Attribute::LIFETIME, lifetime);
+
+        let (ax, ay) = self.acceleration;
+        let accel = module.lit(Vec3::new(ax, ay, 0.));
+        let update_accel = AccelModifier::new(accel);
+
+        let spawner = match self.spawn {
+            EffectSpawn::Rate(rate) => Spawner::rate(rate.into()),
+            EffectSpawn::Once(count) => Spawner::once(count.into(), true),
+            EffectSpawn::Burst { count, period } => Spawner::burst(count.into(), period.into()),
+        };
+
+        let (min_height, max_height) = self.height.unwrap_or(self.size);
+
+        let mut effect = EffectAsset::new(vec![self.capacity], spawner, module)
+            .with_name(name)
+            .init(init_pos)
+            .init(init_vel)
+            .init(init_lifetime)
+            .update(update_accel)
+            .render(SetSizeModifier {
+                size: CpuValue::Uniform((
+                    Vec2::new(self.size.0, min_height),
+                    Vec2::new(self.size.1, max_height),
+                )),
+            })
+            .render(ColorOverLifetimeModifier { gradient });
+
+        if self.along_velocity {
+            effect = effect.render(OrientModifier::new(OrientMode::AlongVelocity));
+        }
+
+        effect
+    }
+}
+
+/// A random unit vector in the XY plane.
+fn random_direction(module: &mut Module) -> ExprHandle {
+    let random = module.builtin(BuiltInOperator::Rand(VectorType::VEC3F.into()));
+    let half = module.lit(Vec3::splat(0.5));
+    let offset = module.sub(random, half);
+    // Flatten before normalizing, or the random z would shorten the XY part.
+    let flat = module.lit(Vec3::new(1., 1., 0.));
+    let offset = module.mul(offset, flat);
+    module.normalize(offset)
+}
+
+/// The built effects, by name. Handles stay the same when the library is edited, so
+/// playing effects pick up the changes.
+#[derive(Resource)]
+pub struct EffectRegistry {
+    library: Handle<EffectLibrary>,
+    effects: HashMap<String, RegisteredEffect>,
+    /// Whether the library has loaded, after which unknown names are mistakes.
+    loaded: bool,
+}
+
+#[derive(Debug, Clone)]
+struct RegisteredEffect {
+    handle: Handle<EffectAsset>,
+    duration: f32,
+}
+
+impl FromWorld for EffectRegistry {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            library: world
+                .resource::<AssetServer>()
+                .load("particles.effects.ron"),
+            effects: HashMap::default(),
+            loaded: false,
+        }
+    }
+}
+
+impl EffectRegistry {
+    pub fn get(&self, name: &str) -> Option<&Handle<EffectAsset>> {
+        self.effects.get(name).map(|effect| &effect.handle)
+    }
+}
+
+fn build_registry(
+    mut events: EventReader<AssetEvent<EffectLibrary>>,
+    mut registry: ResMut<EffectRegistry>,
+    libraries: Res<Assets<EffectLibrary>>,
+    mut effects: ResMut<Assets<EffectAsset>>,
+) {
+    let id = registry.library.id();
+    let reloaded = events
+        .read()
+        .any(|event| event.is_loaded_with_dependencies(id) || event.is_modified(id));
+    if !reloaded {
+        return;
+    }
+
+    let Some(library) = libraries.get(id) else {
+        return;
+    };
+
+    for (name, definition) in &library.effects {
+        let asset = definition.build(name);
+        let duration = definition.duration();
+
+        match registry.effects.get_mut(name) {
+            Some(effect) => {
+                effects.insert(&effect.handle, asset);
+                effect.duration = duration;
+            }
+            None => {
+                let handle = effects.add(asset);
+                registry
+                    .effects
+                    .insert(name.clone(), RegisteredEffect { handle, duration });
+            }
+        }
+    }
+    registry.loaded = true;
+}
+
+/// Plays a one-shot effect from the [`EffectRegistry`], despawned once its particles
+/// have died.
+#[derive(Event, Debug, Clone)]
+pub struct PlayEffect {
+    pub name: String,
+    /// Translation relative to the LDtk world.
+    pub translation: Vec2,
+}
+
+impl PlayEffect {
+    pub fn new(name: impl Into<String>, translation: Vec2) -> Self {
+        Self {
+            name: name.into(),
+            translation,
+        }
+    }
+}
+
+#[derive(Component, Deref, DerefMut)]
+struct Burst(Timer);
+
+/// Effects play in front of the level's tiles.
+const EFFECT_Z: f32 = 10.;
+
+fn play_effects(
+    mut commands: Commands,
+    mut play: EventReader<PlayEffect>,
+    registry: Res<EffectRegistry>,
+    worlds: Query<Entity, With<Handle<LdtkProject>>>,
+) {
+    let Ok(world) = worlds.get_single() else {
+        play.clear();
+        return;
+    };
+
+    for event in play.read() {
+        let Some(effect) = registry.effects.get(&event.name) else {
+            if registry.loaded {
+                warn!("no particle effect named {:?}", event.name);
+            }
+            continue;
+        };
+
+        commands.entity(world).with_children(|world| {
+            world.spawn((
+                ParticleEffectBundle {
+                    effect: ParticleEffect::new(effect.handle.clone()),
+                    transform: Transform::from_translation(event.translation.extend(EFFECT_Z)),
+                    ..Default::default()
+                },
+                Burst(Timer::from_seconds(effect.duration, TimerMode::Once)),
+            ));
+        });
+    }
+}
+
+fn despawn_bursts(
+    mut commands: Commands,
+    mut bursts: Query<(Entity, &mut Burst)>,
+    time: Res<Time>,
+) {
+    for (entity, mut burst) in &mut bursts {
+        if burst.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn burst_on_gameplay_events(
+    mut play: EventWriter<PlayEffect>,
+    mut wall_bounces: EventReader<WallBounce>,
+    mut goal_reached: EventReader<GoalReached>,
+    transforms: Query<&Transform>,
+    player: Query<&Transform, With<Player>>,
+) {
+    for event in wall_bounces.read() {
+        if let Ok(transform) = transforms.get(event.entity) {
+            play.send(PlayEffect::new(
+                WALL_BOUNCE_EFFECT,
+                transform.translation.truncate(),
+            ));
+        }
+    }
+
+    if goal_reached.read().count() > 0 {
+        if let Ok(transform) = player.get_single() {
+            play.send(PlayEffect::new(
+                GOAL_EFFECT,
+                transform.translation.truncate(),
+            ));
+        }
+    }
+}
+
+/// Whether the player kicks up dust and sparks. Turned off for performance, or for
+/// players bothered by the flicker.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PlayerParticles {
+    #[default]
+    On,
+    Off,
+}
+
+impl PlayerParticles {
+    pub fn next(self) -> Self {
+        match self {
+            Self::On => Self::Off,
+            Self::Off => Self::On,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::On => "Dust: On",
+            Self::Off => "Dust: Off",
+        }
+    }
+}
+
+/// The bottom of an entity's collider, relative to the LDtk world.
+fn feet(transform: &Transform, collider: &Collider) -> Vec2 {
+    let bottom = collider.shape().compute_local_aabb().mins.y;
+    Vec2::new(
+        transform.translation.x,
+        transform.translation.y + bottom * transform.scale.y.abs(),
+    )
+}
+
+/// Still reads the events while [`PlayerParticles::Off`], so turning particles back on
+/// doesn't kick up dust for a jump or landing from the frame before.
+fn kick_up_dust(
+    mut play: EventWriter<PlayEffect>,
+    mut jumped: EventReader<Jumped>,
+    mut fell: EventReader<PlayerFell>,
+    bodies: Query<(&Transform, &Collider)>,
+    player: Query<(&Transform, &Collider), With<Player>>,
+    tuning: JumpTuning,
+    particles: Res<PlayerParticles>,
+) {
+    if *particles == PlayerParticles::Off {
+        jumped.clear();
+        fell.clear();
+        return;
+    }
+
+    for event in jumped.read() {
+        if let Ok((transform, collider)) = bodies.get(event.entity) {
+            play.send(PlayEffect::new(JUMP_EFFECT, feet(transform, collider)));
+        }
+    }
+
+    let Ok((transform, collider)) = player.get_single() else {
+        fell.clear();
+        return;
+    };
+
+    for event in fell.read() {
+        // The same falls that splat the player.
+        let name = if event.distance >= tuning.profile().splat_distance {
+            LONG_FALL_EFFECT
+        } else {
+            LAND_EFFECT
+        };
+        play.send(PlayEffect::new(name, feet(transform, collider)));
+    }
+}
+
+/// Seconds between sparks at the start and at the end of a charge.
+const SPARK_INTERVAL: (f32, f32) = (0.25, 0.04);
+
+/// Plays sparks more and more often as the charge builds.
+fn charge_sparks(
+    mut play: EventWriter<PlayEffect>,
+    player: Query<(&Transform, &Collider, &JuiceMeter), With<Player>>,
+    tuning: JumpTuning,
+    mut since_spark: Local<f32>,
+    time: Res<Time>,
+    particles: Res<PlayerParticles>,
+) {
+    let Ok((transform, collider, JuiceMeter::Charging(charge))) = player.get_single() else {
+        *since_spark = 0.;
+        return;
+    };
+    if *particles == PlayerParticles::Off {
+        *since_spark = 0.;
+        return;
+    }
+
+    let progress = (charge / tuning.profile().max_charge).clamp(0., 1.);
+    let interval = SPARK_INTERVAL.0 + (SPARK_INTERVAL.1 - SPARK_INTERVAL.0) * progress;
+
+    *since_spark += time.delta_seconds();
+    if *since_spark >= interval {
+        *since_spark = 0.;
+        play.send(PlayEffect::new(CHARGE_EFFECT, feet(transform, collider)));
+    }
+}
+
+#[derive(Default, Bundle, LdtkEntity)]
+struct EmitterBundle {
+    #[from_entity_instance]
+    emitter: Emitter,
+}
+
+/// Plays a looping effect from the [`EffectRegistry`] where it is placed in a level.
+#[derive(Component, Debug, Default)]
+pub struct Emitter {
+    pub effect: String,
+    /// Draws the particles with the background instead of the level.
+    pub background: bool,
+}
+
+impl From<&EntityInstance> for Emitter {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        Self {
+            effect: entity_instance
+                .get_string_field("Effect")
+                .cloned()
+                .unwrap_or_default(),
+            background: entity_instance
+                .get_bool_field("Background")
+                .copied()
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Marks emitters whose effect has been spawned, or that name no effect.
+#[derive(Component)]
+struct EmitterStarted;
+
+fn spawn_emitters(
+    mut commands: Commands,
+    emitters: Query<(Entity, &Emitter), Without<EmitterStarted>>,
+    registry: Res<EffectRegistry>,
+) {
+    if !registry.loaded {
+        return;
+    }
+
+    for (entity, emitter) in &emitters {
+        commands.entity(entity).insert(EmitterStarted);
+
+        let Some(handle) = registry.get(&emitter.effect) else {
+            warn!("emitter plays unknown particle effect {:?}", emitter.effect);
+            continue;
+        };
+
+        let layer = if emitter.background {
+            BACKGROUND_LAYER
+        } else {
+            0
+        };
+
+        commands.entity(entity).with_children(|emitter| {
+            emitter.spawn((
+                ParticleEffectBundle {
+                    effect: ParticleEffect::new(handle.clone()),
+                    ..Default::default()
+                },
+                RenderLayers::layer(layer),
+            ));
+        });
+    }
+}
+
+#[derive(Default)]
+struct EffectLibraryLoader;
+
+#[derive(Debug)]
+pub enum EffectLibraryError {
+    Io(io::Error),
+    Ron(SpannedError),
+}
+
+impl fmt::Display for EffectLibraryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "could not read particle effects: {err}"),
+            Self::Ron(err) => write!(f, "invalid particle effects: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for EffectLibraryError {}
+
+impl AssetLoader for EffectLibraryLoader {
+    type Asset = EffectLibrary;
+    type Settings = ();
+    type Error = EffectLibraryError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<EffectLibrary, EffectLibraryError> {
+        let mut bytes = Vec::new();
+        reader
+            .read_to_end(&mut bytes)
+            .await
+            .map_err(EffectLibraryError::Io)?;
+
+        ron::de::from_bytes(&bytes).map_err(EffectLibraryError::Ron)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["effects.ron"]
+    }
+}
@@ -0,0 +1,320 @@
+use crate::{
+    camera::GameCamera,
+    map::selected_level,
+    menu::{ContinueGame, NewGame},
+    player::Player,
+    run::RunStats,
+    save::{SaveData, SaveError},
+    speedrun::Speedrun,
+    state::GameState,
+};
+use avian2d::prelude::PhysicsSet;
+use bevy::{prelude::*, render::view::RenderLayers};
+use bevy_ecs_ldtk::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{fs, path::PathBuf, time::Duration};
+
+/// Bumped whenever [`GhostRun`] changes shape.
+pub const GHOST_VERSION: u32 = 1;
+
+/// The ghost is drawn by its own camera, underneath the gameplay layer.
+pub const GHOST_LAYER: usize = 2;
+
+const GHOST_ALPHA: f32 = 0.35;
+
+/// Samples every run and races the player against the personal best.
+pub struct GhostPlugin;
+
+impl Plugin for GhostPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(BestGhost::load())
+            .init_resource::<GhostRecording>()
+            .add_systems(Startup, setup_ghost_camera)
+            .add_systems(Update, reset_recording)
+            .add_systems(
+                FixedPostUpdate,
+                record_ghost
+                    .after(PhysicsSet::Sync)
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(
+                Update,
+                (spawn_ghost, move_ghost)
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(OnEnter(GameState::Victory), save_best_ghost);
+    }
+}
+
+/// The player as it was on one fixed tick.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct GhostSample {
+    /// Index into [`GhostRun::levels`].
+    pub level: u16,
+    /// Translation relative to the LDtk world.
+    pub translation: [f32; 2],
+    pub facing_left: bool,
+    pub atlas_index: u16,
+}
+
+/// One sample per fixed tick of a run, indexed by [`RunStats::ticks`].
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct GhostRun {
+    pub version: u32,
+    /// The run's speedrun time, so the ghost and the splits agree on the personal best.
+    pub time: Duration,
+    pub timestep: Duration,
+    /// The LDtk level iids the samples refer to.
+    pub levels: Vec<String>,
+    pub samples: Vec<GhostSample>,
+}
+
+impl GhostRun {
+    pub fn path() -> Result<PathBuf, SaveError> {
+        SaveData::path().map(|path| path.with_file_name("ghost.ron"))
+    }
+
+    pub fn read() -> Result<Option<Self>, SaveError> {
+        let contents = match fs::read_to_string(Self::path()?) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+
+        let ghost: Self = ron::from_str(&contents).map_err(SaveError::Corrupt)?;
+        if ghost.version != GHOST_VERSION {
+            return Err(SaveError::Version {
+                found: ghost.version,
+                expected: GHOST_VERSION,
+            });
+        }
+
+        Ok(Some(ghost))
+    }
+
+    pub fn write(&self) -> Result<(), SaveError> {
+        let path = Self::path()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        fs::write(path, ron::to_string(self).map_err(SaveError::Serialize)?)?;
+
+        Ok(())
+    }
+
+    fn level_index(&mut self, iid: &str) -> u16 {
+        match self.levels.iter().position(|level| level == iid) {
+            Some(index) => index as u16,
+            None => {
+                self.levels.push(iid.to_owned());
+                self.levels.len() as u16 - 1
+            }
+        }
+    }
+
+    /// The sample taken at the end of the given run tick, counting from one.
+    fn sample_at(&self, tick: u32) -> Option<&GhostSample> {
+        self.samples.get(tick.checked_sub(1)? as usize)
+    }
+}
+
+/// The personal best loaded from disk, if any.
+#[derive(Resource, Default)]
+struct BestGhost(Option<GhostRun>);
+
+impl BestGhost {
+    fn load() -> Self {
+        match GhostRun::read() {
+            Ok(ghost) => Self(ghost),
+            Err(err) => {
+                error!("failed to load ghost: {err}");
+                Self(None)
+            }
+        }
+    }
+}
+
+/// The run being sampled. Resumed runs are missing their start, so only runs begun
+/// with a new game can become the personal best.
+#[derive(Resource, Default)]
+struct GhostRecording {
+    run: GhostRun,
+    eligible: bool,
+}
+
+#[derive(Component)]
+struct Ghost;
+
+fn setup_ghost_camera(mut commands: Commands) {
+    commands.spawn((
+        Camera2dBundle {
+            camera: Camera {
+                hdr: true,
+                order: 0,
+                clear_color: ClearColorConfig::None,
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        GameCamera,
+        RenderLayers::layer(GHOST_LAYER),
+    ));
+}
+
+fn reset_recording(
+    mut new_game: EventReader<NewGame>,
+    mut continue_game: EventReader<ContinueGame>,
+    mut recording: ResMut<GhostRecording>,
+    time: Res<Time<Fixed>>,
+) {
+    let eligible = match (new_game.read().count(), continue_game.read().count()) {
+        (0, 0) => return,
+        (_, 0) => true,
+        _ => false,
+    };
+
+    *recording = GhostRecording {
+        run: GhostRun {
+            version: GHOST_VERSION,
+            timestep: time.timestep(),
+            ..Default::default()
+        },
+        eligible,
+    };
+}
+
+/// Samples the player once per tick of the run clock, skipping ticks the climb was
+/// held on, like while a level spawns.
+fn record_ghost(
+    mut recording: ResMut<GhostRecording>,
+    stats: Res<RunStats>,
+    player: Query<(&Transform, &TextureAtlas), With<Player>>,
+    level_selection: Res<LevelSelection>,
+    projects: Query<&Handle<LdtkProject>>,
+    project_assets: Res<Assets<LdtkProject>>,
+) {
+    if !recording.eligible || recording.run.samples.len() >= stats.ticks as usize {
+        return;
+    }
+
+    let Ok((transform, atlas)) = player.get_single() else {
+        return;
+    };
+
+    let Some(level) = projects
+        .get_single()
+        .ok()
+        .and_then(|handle| project_assets.get(handle))
+        .and_then(|project| selected_level(project, &level_selection))
+    else {
+        return;
+    };
+
+    let level = recording.run.level_index(&level.iid);
+    recording.run.samples.push(GhostSample {
+        level,
+        translation: transform.translation.truncate().to_array(),
+        facing_left: transform.scale.x < 0.,
+        atlas_index: atlas.index as u16,
+    });
+}
+
+fn save_best_ghost(
+    mut recording: ResMut<GhostRecording>,
+    mut best: ResMut<BestGhost>,
+    speedrun: Res<Speedrun>,
+) {
+    if !recording.eligible {
+        return;
+    }
+
+    let is_best = best.0.as_ref().is_none_or(|best| speedrun.time < best.time);
+    if !is_best {
+        return;
+    }
+
+    let mut run = std::mem::take(&mut recording.run);
+    run.time = speedrun.time;
+    recording.eligible = false;
+
+    if let Err(err) = run.write() {
+        error!("failed to write ghost: {err}");
+    }
+
+    best.0 = Some(run);
+}
+
+/// Spawns the ghost next to the player once both exist, copying its sprite sheet.
+fn spawn_ghost(
+    mut commands: Commands,
+    best: Res<BestGhost>,
+    ghosts: Query<(), With<Ghost>>,
+    player: Query<(&Parent, &Sprite, &Handle<Image>, &TextureAtlas), With<Player>>,
+) {
+    if best.0.is_none() || !ghosts.is_empty() {
+        return;
+    }
+
+    let Ok((world, sprite, texture, atlas)) = player.get_single() else {
+        return;
+    };
+
+    commands.entity(world.get()).with_children(|world| {
+        world.spawn((
+            Ghost,
+            SpriteBundle {
+                sprite: Sprite {
+                    color: sprite.color.with_alpha(GHOST_ALPHA),
+                    ..sprite.clone()
+                },
+                texture: texture.clone(),
+                visibility: Visibility::Hidden,
+                ..Default::default()
+            },
+            atlas.clone(),
+            RenderLayers::layer(GHOST_LAYER),
+        ));
+    });
+}
+
+fn move_ghost(
+    best: Res<BestGhost>,
+    stats: Res<RunStats>,
+    mut ghost: Query<(&mut Transform, &mut TextureAtlas, &mut Visibility), With<Ghost>>,
+    player: Query<&Transform, (With<Player>, Without<Ghost>)>,
+    level_selection: Res<LevelSelection>,
+    projects: Query<&Handle<LdtkProject>>,
+    project_assets: Res<Assets<LdtkProject>>,
+) {
+    let Ok((mut transform, mut atlas, mut visibility)) = ghost.get_single_mut() else {
+        return;
+    };
+
+    let current_level = projects
+        .get_single()
+        .ok()
+        .and_then(|handle| project_assets.get(handle))
+        .and_then(|project| selected_level(project, &level_selection));
+
+    let sample = best.0.as_ref().and_then(|run| {
+        let sample = run.sample_at(stats.ticks)?;
+        let level = run.levels.get(sample.level as usize)?;
+        (Some(level) == current_level.map(|level| &level.iid)).then_some(sample)
+    });
+
+    let Some(sample) = sample else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+
+    let scale = player
+        .get_single()
+        .map_or(Vec3::ONE, |player| player.scale.abs());
+
+    *visibility = Visibility::Inherited;
+    transform.translation = Vec2::from_array(sample.translation).extend(transform.translation.z);
+    transform.scale = scale * Vec3::new(if sample.facing_left { -1. } else { 1. }, 1., 1.);
+    atlas.index = sample.atlas_index as usize;
+}
@@ -0,0 +1,205 @@
+use crate::{
+    player::{
+        jump_profile::{ChargeMode, JumpTuning},
+        movement::ChargeTierReached,
+        JuiceMeter, Player,
+    },
+    state::GameState,
+};
+use bevy::prelude::*;
+use std::{f32::consts::TAU, time::Duration};
+
+/// Draws the jump charge meter next to the player and ticks when a charge tier is
+/// reached.
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HudSettings>()
+            .insert_gizmo_config(
+                HudGizmos,
+                GizmoConfig {
+                    line_width: 6.,
+                    ..Default::default()
+                },
+            )
+            .add_systems(
+                Update,
+                (draw_charge_meter, play_tier_ticks).run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct HudSettings {
+    pub charge_meter: ChargeMeter,
+}
+
+/// How the [`JuiceMeter`] is shown. [`ChargeMeter::Hidden`] is for players who want
+/// to judge their jumps by feel alone.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ChargeMeter {
+    #[default]
+    Bar,
+    Ring,
+    Hidden,
+}
+
+impl ChargeMeter {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Bar => Self::Ring,
+            Self::Ring => Self::Hidden,
+            Self::Hidden => Self::Bar,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Bar => "Meter: Bar",
+            Self::Ring => "Meter: Ring",
+            Self::Hidden => "Meter: Off",
+        }
+    }
+}
+
+#[derive(Default, Reflect, GizmoConfigGroup)]
+struct HudGizmos;
+
+const METER_BACKGROUND: Color = Color::srgba(0., 0., 0., 0.5);
+/// Below the profile's minimum charge releasing the jump does nothing.
+const METER_WEAK: Color = Color::srgb(0.6, 0.6, 0.6);
+const METER_READY: Color = Color::srgb(0.3, 0.9, 1.);
+const METER_EXHAUSTED: Color = Color::srgb(1., 0.2, 0.2);
+const METER_TIER_FLASH: Color = Color::WHITE;
+const TIER_FLASH_SECONDS: f32 = 0.08;
+
+/// Size of the meter and its distance from the player's origin, in player space.
+const METER_WIDTH: f32 = 160.;
+const METER_OFFSET: f32 = 200.;
+const RING_RADIUS: f32 = 48.;
+
+fn draw_charge_meter(
+    mut gizmos: Gizmos<HudGizmos>,
+    settings: Res<HudSettings>,
+    player: Query<(&GlobalTransform, &JuiceMeter), With<Player>>,
+    tuning: JumpTuning,
+    mut tiers_reached: EventReader<ChargeTierReached>,
+    mut last_tier_at: Local<Option<f32>>,
+    time: Res<Time>,
+) {
+    if tiers_reached.read().count() > 0 {
+        *last_tier_at = Some(time.elapsed_seconds());
+    }
+
+    if settings.charge_meter == ChargeMeter::Hidden {
+        return;
+    }
+
+    let Ok((transform, juice)) = player.get_single() else {
+        return;
+    };
+
+    let profile = tuning.profile();
+    let threshold = profile.min_charge / profile.max_charge;
+
+    let (fill, color) = match *juice {
+        JuiceMeter::Idle => return,
+        JuiceMeter::Charging(charge) => {
+            let flashing =
+                last_tier_at.is_some_and(|at| time.elapsed_seconds() - at < TIER_FLASH_SECONDS);
+
+            let color = if flashing {
+                METER_TIER_FLASH
+            } else if charge >= profile.min_charge {
+                METER_READY
+            } else {
+                METER_WEAK
+            };
+            ((charge / profile.max_charge).clamp(0., 1.), color)
+        }
+        JuiceMeter::Exhausted => {
+            // Blink until the jump button is let go.
+            if (time.elapsed_seconds() * 8.).fract() < 0.5 {
+                return;
+            }
+            (1., METER_EXHAUSTED)
+        }
+    };
+
+    let (scale, _, translation) = transform.to_scale_rotation_translation();
+    let scale = scale.abs();
+    let center = translation.truncate() + Vec2::Y * METER_OFFSET * scale.y;
+
+    match settings.charge_meter {
+        ChargeMeter::Bar => {
+            let half = Vec2::X * METER_WIDTH * scale.x / 2.;
+            let start = center - half;
+
+            gizmos.line_2d(start, center + half, METER_BACKGROUND);
+            gizmos.line_2d(start, start + half * 2. * fill, color);
+
+            // Tick at the minimum jump charge, and at every tier when charging in tiers.
+            let tick = Vec2::Y * 12. * scale.y;
+            let threshold = start + half * 2. * threshold;
+            gizmos.line_2d(threshold - tick, threshold + tick, METER_READY);
+
+            if tuning.mode() == ChargeMode::Tiers {
+                for tier in 1..profile.tiers {
+                    let at = profile.tier_charge(tier) / profile.max_charge;
+                    let notch = start + half * 2. * at;
+                    gizmos.line_2d(notch - tick / 2., notch + tick / 2., METER_READY);
+                }
+            }
+        }
+        ChargeMeter::Ring => {
+            let radius = RING_RADIUS * scale.y;
+
+            gizmos.circle_2d(center, radius, METER_BACKGROUND);
+            // Arcs are centred on their direction, so this fills clockwise from the top.
+            let arc = TAU * fill;
+            gizmos.arc_2d(center, arc / 2., arc, radius, color);
+
+            // The same ticks as the bar, pointing out of the ring.
+            let tick = 12. * scale.y;
+            let notch = |at: f32| {
+                let (sin, cos) = (TAU * at).sin_cos();
+                Vec2::new(sin, cos)
+            };
+            let threshold = notch(threshold);
+            gizmos.line_2d(
+                center + threshold * (radius - tick),
+                center + threshold * (radius + tick),
+                METER_READY,
+            );
+
+            if tuning.mode() == ChargeMode::Tiers {
+                for tier in 1..profile.tiers {
+                    let at = notch(profile.tier_charge(tier) / profile.max_charge);
+                    gizmos.line_2d(
+                        center + at * (radius - tick / 2.),
+                        center + at * (radius + tick / 2.),
+                        METER_READY,
+                    );
+                }
+            }
+        }
+        ChargeMeter::Hidden => {}
+    }
+}
+
+/// A short beep for each tier, rising with the tier.
+fn play_tier_ticks(
+    mut commands: Commands,
+    mut tiers_reached: EventReader<ChargeTierReached>,
+    mut pitches: ResMut<Assets<Pitch>>,
+) {
+    for reached in tiers_reached.read() {
+        let frequency = 440. * 2f32.powf(reached.tier as f32 * 2. / 12.);
+
+        commands.spawn(PitchBundle {
+            source: pitches.add(Pitch::new(frequency, Duration::from_millis(60))),
+            settings: PlaybackSettings::DESPAWN,
+        });
+    }
+}
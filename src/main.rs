@@ -1,16 +1,36 @@
+// Systems take their resources and queries as parameters, so long signatures are normal.
+#![allow(clippy::too_many_arguments, clippy::type_complexity)]
+
 use avian2d::{dynamics::integrator::Gravity, PhysicsPlugins};
-use bevy::{
-    input::{keyboard::KeyboardInput, ButtonState},
-    prelude::*,
-    window::WindowResolution,
-};
-use bevy_inspector_egui::quick::WorldInspectorPlugin;
+use bevy::prelude::*;
+use camera::CameraPlugin;
+use display::DisplayPlugin;
+use effects::EffectsPlugin;
+use ghost::GhostPlugin;
+use hud::HudPlugin;
 use map::MapPlugin;
+use menu::MenuPlugin;
 use player::PlayerPlugin;
+use replay::ReplayPlugin;
+use run::RunPlugin;
+use save::SavePlugin;
+use speedrun::SpeedrunPlugin;
+use state::StatePlugin;
 
 pub mod animated_sprites;
+pub mod camera;
+pub mod display;
+pub mod effects;
+pub mod ghost;
+pub mod hud;
 pub mod map;
+pub mod menu;
 pub mod player;
+pub mod replay;
+pub mod run;
+pub mod save;
+pub mod speedrun;
+pub mod state;
 
 const GRAVITY: f32 = 2048.;
 
@@ -21,35 +41,30 @@ fn main() {
                 .set(WindowPlugin {
                     primary_window: Some(Window {
                         title: "jump wiz".into(),
-                        resolution: WindowResolution::new(map::WINDOW_SIZE, map::WINDOW_SIZE),
+                        resolution: display::window_resolution(),
                         ..Default::default()
                     }),
                     ..Default::default()
                 })
                 .set(ImagePlugin::default_nearest()),
-            PhysicsPlugins::default(),
+            // Physics steps with the fixed timestep so that replays are deterministic.
+            PhysicsPlugins::new(FixedPostUpdate),
+            StatePlugin,
+            MenuPlugin,
+            RunPlugin,
+            SavePlugin,
+            ReplayPlugin,
+            GhostPlugin,
+            HudPlugin,
+            SpeedrunPlugin,
             PlayerPlugin,
+            CameraPlugin,
+            DisplayPlugin,
+            EffectsPlugin,
             // PhysicsDebugPlugin::default(),
-            // WorldInspectorPlugin::new(),
+            // bevy_inspector_egui::quick::WorldInspectorPlugin::new(),
             MapPlugin,
         ))
-        .add_systems(Update, close_on_escape)
         .insert_resource(Gravity(Vec2::NEG_Y * GRAVITY))
         .run();
 }
-
-fn close_on_escape(mut input: EventReader<KeyboardInput>, mut writer: EventWriter<AppExit>) {
-    for e in input.read() {
-        if matches!(e, KeyboardInput {
-
-            key_code,
-            state,
-            ..
-        }
-
-            if *key_code == KeyCode::Escape && *state == ButtonState::Pressed
-        ) {
-            writer.send(AppExit::Success);
-        }
-    }
-}
@@ -1,24 +1,48 @@
-use avian2d::{collision::Collider, dynamics::rigid_body::RigidBody};
+use crate::{camera::GameCamera, player::Player, state::GameState};
+use avian2d::prelude::{Collider, CollisionStarted, PhysicsSet, RigidBody, Sensor};
 use bevy::core_pipeline::bloom::{BloomCompositeMode, BloomPrefilterSettings, BloomSettings};
 use bevy::prelude::*;
 use bevy::render::view::{ColorGrading, ColorGradingGlobal, RenderLayers};
-use bevy_ecs_ldtk::prelude::*;
-use bevy_hanabi::prelude::*;
+use bevy_ecs_ldtk::{assets::LevelIndices, ldtk::Level, prelude::*};
 
-pub const WINDOW_SIZE: f32 = 1000.0;
-const TILE_SIZE: f32 = 512.0;
-const TILE_MAP_SIZE: f32 = 16.0;
+/// Side of the square of world units every camera shows, independent of the window.
+pub const VIEW_SIZE: f32 = 1000.0;
+
+pub mod background;
+pub mod collision;
+pub mod gravity;
+pub mod hazard;
+pub mod platform;
+pub mod transition;
+pub mod wind;
 
 pub struct MapPlugin;
 
 impl Plugin for MapPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins((LdtkPlugin, HanabiPlugin))
-            .register_ldtk_entity::<GoalBundle>("Goal")
-            .register_ldtk_int_cell_for_layer::<ColliderBundle>("Collision", 1)
-            .insert_resource(LevelSelection::index(0))
-            .add_systems(Startup, (setup, setup_effect))
-            .add_systems(Update, init_added_collision);
+        app.add_plugins((
+            LdtkPlugin,
+            background::BackgroundPlugin,
+            collision::CollisionPlugin,
+            transition::LevelTransitionPlugin,
+            wind::WindPlugin,
+            platform::PlatformPlugin,
+            gravity::GravityZonePlugin,
+            hazard::HazardPlugin,
+        ))
+        .register_ldtk_entity::<GoalBundle>("Goal")
+        .add_event::<GoalReached>()
+        .insert_resource(LevelSelection::index(0))
+        .init_resource::<LevelScale>()
+        .add_systems(Startup, setup)
+        .add_systems(Update, (update_level_scale, apply_level_scale).chain())
+        .add_systems(
+            FixedPostUpdate,
+            detect_goal
+                .after(PhysicsSet::Sync)
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(OnExit(GameState::Victory), reset_world);
     }
 }
 
@@ -26,25 +50,91 @@ impl Plugin for MapPlugin {
 struct GoalBundle {
     #[sprite_sheet_bundle]
     sprite_sheet_bundle: LdtkSpriteSheetBundle,
+    #[from_entity_instance]
+    sensor: GoalSensorBundle,
+    goal: Goal,
+}
+
+#[derive(Component, Default)]
+pub struct Goal;
+
+#[derive(Default, Bundle)]
+struct GoalSensorBundle {
+    rigid_body: RigidBody,
+    collider: Collider,
+    sensor: Sensor,
 }
 
-#[derive(Clone, Bundle, LdtkIntCell)]
-pub struct ColliderBundle {
-    int_cell_collider: IntCellCollider,
-    transform: Transform,
+impl From<&EntityInstance> for GoalSensorBundle {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        Self {
+            rigid_body: RigidBody::Static,
+            collider: Collider::rectangle(
+                entity_instance.width as f32,
+                entity_instance.height as f32,
+            ),
+            sensor: Sensor,
+        }
+    }
 }
 
-impl Default for ColliderBundle {
+/// Sent when the [`Player`] touches the [`Goal`].
+#[derive(Event, Debug)]
+pub struct GoalReached;
+
+/// How the selected LDtk level is fitted onto the screen, read from the loaded
+/// [`LdtkProject`] rather than assumed.
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct LevelScale {
+    /// Size of the selected level in LDtk pixels.
+    pub level_size: Vec2,
+    /// World units per LDtk pixel.
+    pub scale: f32,
+}
+
+impl Default for LevelScale {
     fn default() -> Self {
         Self {
-            int_cell_collider: Default::default(),
-            transform: Transform::default(),
+            level_size: Vec2::splat(VIEW_SIZE),
+            scale: 1.,
+        }
+    }
+}
+
+impl LevelScale {
+    pub fn from_level(level: &Level) -> Self {
+        let level_size = Vec2::new(level.px_wid as f32, level.px_hei as f32);
+
+        Self {
+            level_size,
+            scale: VIEW_SIZE / level_size.max_element(),
         }
     }
 }
 
-#[derive(Default, Clone, Component)]
-struct IntCellCollider;
+/// Looks up the raw LDtk level currently picked by the [`LevelSelection`].
+pub fn selected_level<'a>(
+    project: &'a LdtkProject,
+    selection: &LevelSelection,
+) -> Option<&'a Level> {
+    project
+        .iter_raw_levels()
+        .enumerate()
+        .find(|(i, level)| selection.is_match(&LevelIndices::in_root(*i), level))
+        .map(|(_, level)| level)
+}
+
+/// The iid of the level picked by the [`LevelSelection`]. Selections by iid are answered
+/// without looking through the project.
+pub fn selected_level_iid(
+    project: Option<&LdtkProject>,
+    selection: &LevelSelection,
+) -> Option<String> {
+    match selection {
+        LevelSelection::Iid(iid) => Some(iid.as_str().to_owned()),
+        _ => selected_level(project?, selection).map(|level| level.iid.clone()),
+    }
+}
 
 fn setup(
     mut commands: Commands,
@@ -60,7 +150,8 @@ fn setup(
         Camera2dBundle {
             camera: Camera {
                 hdr: true,
-                order: 0,
+                // Drawn over the ghost camera, see `ghost::GHOST_LAYER`.
+                order: 1,
                 clear_color: ClearColorConfig::None,
                 ..Default::default()
             },
@@ -77,24 +168,19 @@ fn setup(
             },
             composite_mode: BloomCompositeMode::Additive,
         },
+        GameCamera,
         RenderLayers::layer(0),
     ));
     commands.spawn((
         LdtkWorldBundle {
             ldtk_handle: asset_server.load("map.ldtk"),
-            transform: Transform::default()
-                .with_scale(Vec3::new(
-                    WINDOW_SIZE / (TILE_SIZE * TILE_MAP_SIZE / 2.0),
-                    WINDOW_SIZE / (TILE_SIZE * TILE_MAP_SIZE / 2.0),
-                    1.,
-                ))
-                .with_translation(Vec3::new(-WINDOW_SIZE / 2.0, -WINDOW_SIZE / 2.0, 0.0)),
             ..Default::default()
         },
         RenderLayers::layer(0),
     ));
     settings.set_clear_color = SetClearColor::No;
     settings.level_background = LevelBackground::Nonexistent;
+    settings.level_spawn_behavior = LevelSpawnBehavior::UseZeroTranslation;
 
     commands.spawn((
         Camera2dBundle {
@@ -135,154 +221,73 @@ fn setup(
         //     },
         //     ..default()
         // },
-        RenderLayers::layer(1),
-    ));
-    commands.spawn((
-        SpriteBundle {
-            transform: Transform::default().with_scale(Vec3::new(
-                WINDOW_SIZE / (TILE_SIZE * TILE_MAP_SIZE / 2.0),
-                WINDOW_SIZE / (TILE_SIZE * TILE_MAP_SIZE / 2.0),
-                1.,
-            )),
-            //.with_translation(Vec3::new(-WINDOW_SIZE / 2.0, -WINDOW_SIZE / 2.0, 0.0)),
-            texture: asset_server.load("background/simplified/Level_0/Walls2.png"),
-            sprite: Sprite {
-                // color: Color::LinearRgba(LinearRgba::new(0.0, 0.0, 0.0, 1.0)),
-                ..Default::default()
-            },
-            ..Default::default()
-        },
-        RenderLayers::layer(1),
-    ));
-    commands.spawn((
-        SpriteBundle {
-            transform: Transform::default().with_scale(Vec3::new(
-                WINDOW_SIZE / (TILE_SIZE * TILE_MAP_SIZE / 2.0),
-                WINDOW_SIZE / (TILE_SIZE * TILE_MAP_SIZE / 2.0),
-                1.,
-            )),
-            //.with_translation(Vec3::new(-WINDOW_SIZE / 2.0, -WINDOW_SIZE / 2.0, 0.0)),
-            texture: asset_server.load("background/simplified/Level_0/Background_decor2.png"),
-            ..Default::default()
-        },
-        RenderLayers::layer(1),
-    ));
-    commands.spawn((
-        SpriteBundle {
-            transform: Transform::default().with_scale(Vec3::new(
-                WINDOW_SIZE / (TILE_SIZE * TILE_MAP_SIZE / 2.0),
-                WINDOW_SIZE / (TILE_SIZE * TILE_MAP_SIZE / 2.0),
-                1.,
-            )),
-            //.with_translation(Vec3::new(-WINDOW_SIZE / 2.0, -WINDOW_SIZE / 2.0, 0.0)),
-            texture: asset_server.load("background/simplified/Level_0/Plants2.png"),
-            ..Default::default()
-        },
-        RenderLayers::layer(1),
+        GameCamera,
+        RenderLayers::layer(background::BACKGROUND_LAYER),
     ));
 }
 
-fn collision_tile_size() -> f32 {
-    TILE_SIZE * WINDOW_SIZE / (TILE_SIZE * TILE_MAP_SIZE / 2.0) / 2.0
+fn detect_goal(
+    mut collisions: EventReader<CollisionStarted>,
+    mut goal_reached: EventWriter<GoalReached>,
+    goals: Query<(), With<Goal>>,
+    player: Query<(), With<Player>>,
+) {
+    for CollisionStarted(a, b) in collisions.read() {
+        if (goals.contains(*a) && player.contains(*b))
+            || (goals.contains(*b) && player.contains(*a))
+        {
+            goal_reached.send(GoalReached);
+        }
+    }
 }
 
-fn init_added_collision(
+/// Respawns the LDtk world from the first level so the next climb starts over. The
+/// player is worldly and is respawned along with it.
+fn reset_world(
     mut commands: Commands,
-    cells: Query<(Entity, &GridCoords), Added<IntCellCollider>>,
+    worlds: Query<Entity, With<Handle<LdtkProject>>>,
+    mut level_selection: ResMut<LevelSelection>,
 ) {
-    for (entity, coords) in cells.iter() {
-        commands.spawn((
-            RigidBody::Static,
-            Collider::rectangle(collision_tile_size(), collision_tile_size()),
-            Transform::default().with_translation(Vec3::new(
-                (coords.x as f32 - TILE_MAP_SIZE / 2.0) * collision_tile_size()
-                    + collision_tile_size() / 2.0,
-                (coords.y as f32 - TILE_MAP_SIZE / 2.0) * collision_tile_size()
-                    + collision_tile_size() / 2.0,
-                0.0,
-            )),
-        ));
+    *level_selection = LevelSelection::index(0);
 
-        commands.get_entity(entity).map(|mut e| e.despawn());
+    for world in &worlds {
+        commands.entity(world).insert(Respawn);
     }
 }
 
-fn setup_effect(mut commands: Commands, mut effects: ResMut<Assets<EffectAsset>>) {
-    // Define a color gradient from red to transparent black
-    let mut gradient = Gradient::new();
-    gradient.add_key(0.0, Vec4::new(0., 0.8, 0.2, 1.));
-    gradient.add_key(1.0, Vec4::splat(0.));
-
-    // Create a new expression module
-    let mut module = Module::default();
-
-    // On spawn, randomly initialize the position of the particle
-    // to be over the surface of a sphere of radius 2 units.
-    let init_pos = SetPositionSphereModifier {
-        center: module.lit(Vec3::ZERO),
-        radius: module.lit(500.),
-        dimension: ShapeDimension::Surface,
-    };
-
-    // Also initialize a radial initial velocity to 6 units/sec
-    // away from the (same) sphere center.
-    let init_vel = SetVelocitySphereModifier {
-        center: module.lit(Vec3::new(-20., -20., 0.)),
-        speed: module.lit(20.),
-    };
-
-    let init_size = SetSizeModifier {
-        size: CpuValue::Uniform((Vec2::splat(2.), Vec2::splat(8.))),
+fn update_level_scale(
+    mut level_scale: ResMut<LevelScale>,
+    level_selection: Res<LevelSelection>,
+    projects: Query<&Handle<LdtkProject>>,
+    project_assets: Res<Assets<LdtkProject>>,
+) {
+    let Some(project) = projects
+        .get_single()
+        .ok()
+        .and_then(|handle| project_assets.get(handle))
+    else {
+        return;
     };
 
-    // Initialize the total lifetime of the particle, that is
-    // the time for which it's simulated and rendered. This modifier
-    // is almost always required, otherwise the particles won't show.
-    let lifetime = module.lit(10.); // literal value "10.0"
-    let init_lifetime = SetAttributeModifier::new(Attribute::LIFETIME, lifetime);
-
-    // Every frame, add a gravity-like acceleration downward
-    let accel = module.lit(Vec3::new(0., 0., 0.));
-    let update_accel = AccelModifier::new(accel);
+    if let Some(level) = selected_level(project, &level_selection) {
+        level_scale.set_if_neq(LevelScale::from_level(level));
+    }
+}
 
-    // Create the effect asset
-    let effect = EffectAsset::new(
-        // Maximum number of particles alive at a time
-        vec![32768],
-        // Spawn at a rate of 5 particles per second
-        Spawner::rate(5.0.into()),
-        // Move the expression module into the asset
-        module,
-    )
-    .with_name("MyEffect")
-    .init(init_pos)
-    .init(init_vel)
-    .init(init_lifetime)
-    .update(update_accel)
-    .render(init_size)
-    // Render the particles with a color gradient over their
-    // lifetime. This maps the gradient key 0 to the particle spawn
-    // time, and the gradient key 1 to the particle death (10s).
-    .render(ColorOverLifetimeModifier { gradient });
+/// Scales the LDtk world so the selected level fills the view,
+/// centred on the origin.
+fn apply_level_scale(
+    level_scale: Res<LevelScale>,
+    mut worlds: Query<&mut Transform, With<Handle<LdtkProject>>>,
+) {
+    if !level_scale.is_changed() {
+        return;
+    }
 
-    // Insert into the asset system
-    let effect_asset = effects.add(effect);
+    let scale = Vec3::new(level_scale.scale, level_scale.scale, 1.);
 
-    commands.spawn((
-        ParticleEffectBundle {
-            effect: ParticleEffect::new(effect_asset.clone()),
-            transform: Transform::from_translation(Vec3::Y).with_scale(Vec3::splat(5.)),
-            ..Default::default()
-        },
-        RenderLayers::layer(0),
-    ));
-
-    commands.spawn((
-        ParticleEffectBundle {
-            effect: ParticleEffect::new(effect_asset),
-            transform: Transform::from_translation(Vec3::Y).with_scale(Vec3::splat(5.)),
-            ..Default::default()
-        },
-        RenderLayers::layer(1),
-    ));
+    for mut transform in worlds.iter_mut() {
+        transform.scale = scale;
+        transform.translation = (-level_scale.level_size * level_scale.scale / 2.0).extend(0.0);
+    }
 }
@@ -0,0 +1,320 @@
+use super::{selected_level, LevelScale};
+use crate::camera::{move_cameras, GameCamera};
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext, LoadState},
+    prelude::*,
+    render::view::RenderLayers,
+    transform::TransformSystem,
+    utils::HashMap,
+};
+use bevy_ecs_ldtk::prelude::*;
+use ron::error::SpannedError;
+use serde::Deserialize;
+use std::{fmt, io};
+
+/// Drawn by the background camera, behind the ghost and the level.
+pub const BACKGROUND_LAYER: usize = 1;
+
+/// Spawns the layers of the selected level's LDtk simplified export behind the level,
+/// styled by `assets/map.background.ron`.
+pub struct BackgroundPlugin;
+
+impl Plugin for BackgroundPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<BackgroundConfig>()
+            .init_asset::<SimplifiedLevel>()
+            .init_asset_loader::<BackgroundConfigLoader>()
+            .init_asset_loader::<SimplifiedLevelLoader>()
+            .init_resource::<ActiveBackground>()
+            .add_systems(
+                Update,
+                (load_level_background, spawn_background_layers).chain(),
+            )
+            .add_systems(
+                PostUpdate,
+                apply_parallax
+                    .after(move_cameras)
+                    .before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+/// Where the simplified export lives and how its layers are drawn.
+#[derive(Asset, TypePath, Deserialize, Debug, Clone)]
+pub struct BackgroundConfig {
+    /// Directory holding a folder per level identifier, relative to `assets`.
+    pub export_dir: String,
+    /// The background level shown behind each map level, by identifier. Map levels not
+    /// listed use the background level of the same name.
+    #[serde(default)]
+    pub levels: HashMap<String, String>,
+    /// Styles by layer file name, layers not listed use [`LayerStyle::default`].
+    #[serde(default)]
+    pub layers: HashMap<String, LayerStyle>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct LayerStyle {
+    /// How far the layer moves with the level as the camera pans, 1 is fixed to the
+    /// level and 0 to the screen.
+    pub parallax: f32,
+    /// Multiplied with the layer's colors, in sRGB with alpha.
+    pub tint: [f32; 4],
+    /// Skips the layer, for those the LDtk map already draws.
+    pub hidden: bool,
+}
+
+impl Default for LayerStyle {
+    fn default() -> Self {
+        Self {
+            parallax: 1.,
+            tint: [1.; 4],
+            hidden: false,
+        }
+    }
+}
+
+/// The parts of a level's simplified export `data.json` that the background needs.
+#[derive(Asset, TypePath, Deserialize, Debug, Clone)]
+pub struct SimplifiedLevel {
+    pub identifier: String,
+    /// Layer images next to `data.json`, from the bottom up.
+    pub layers: Vec<String>,
+}
+
+/// The config and the export of the level whose background is shown.
+#[derive(Resource)]
+struct ActiveBackground {
+    config: Handle<BackgroundConfig>,
+    level: Option<Handle<SimplifiedLevel>>,
+    /// Whether the layers of `level` are spawned with the current config.
+    spawned: bool,
+}
+
+impl FromWorld for ActiveBackground {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            config: world.resource::<AssetServer>().load("map.background.ron"),
+            level: None,
+            spawned: false,
+        }
+    }
+}
+
+/// A layer of the simplified export, scaled like the level.
+#[derive(Component, Debug)]
+struct BackgroundLayer {
+    parallax: f32,
+}
+
+/// Starts loading the export of the selected level when it changes.
+fn load_level_background(
+    mut background: ResMut<ActiveBackground>,
+    configs: Res<Assets<BackgroundConfig>>,
+    asset_server: Res<AssetServer>,
+    level_selection: Res<LevelSelection>,
+    projects: Query<&Handle<LdtkProject>>,
+    project_assets: Res<Assets<LdtkProject>>,
+) {
+    if background.level.is_some() && !level_selection.is_changed() && !configs.is_changed() {
+        return;
+    }
+
+    let Some(config) = configs.get(&background.config) else {
+        return;
+    };
+
+    let Some(level) = projects
+        .get_single()
+        .ok()
+        .and_then(|handle| project_assets.get(handle))
+        .and_then(|project| selected_level(project, &level_selection))
+    else {
+        return;
+    };
+
+    let identifier = config
+        .levels
+        .get(&level.identifier)
+        .unwrap_or(&level.identifier);
+    let path = format!("{}/{identifier}/data.json", config.export_dir);
+    let handle = asset_server.load(path);
+    if background.level.as_ref() != Some(&handle) {
+        background.level = Some(handle);
+        background.spawned = false;
+    }
+}
+
+/// Replaces the background layers once the export has loaded, and again whenever the
+/// config is edited. If the export fails to load, the previous level's layers are
+/// removed rather than left behind a level they don't belong to.
+fn spawn_background_layers(
+    mut commands: Commands,
+    mut background: ResMut<ActiveBackground>,
+    mut config_events: EventReader<AssetEvent<BackgroundConfig>>,
+    configs: Res<Assets<BackgroundConfig>>,
+    levels: Res<Assets<SimplifiedLevel>>,
+    layers: Query<Entity, With<BackgroundLayer>>,
+    asset_server: Res<AssetServer>,
+) {
+    let config_id = background.config.id();
+    if config_events
+        .read()
+        .any(|event| event.is_modified(config_id))
+    {
+        background.spawned = false;
+    }
+
+    if background.spawned {
+        return;
+    }
+
+    let Some(config) = configs.get(&background.config) else {
+        return;
+    };
+    let Some(handle) = background.level.as_ref() else {
+        return;
+    };
+
+    if let Some(LoadState::Failed(err)) = asset_server.get_load_state(handle) {
+        warn!("no background for the level, clearing it: {err}");
+        for entity in &layers {
+            commands.entity(entity).despawn_recursive();
+        }
+        background.spawned = true;
+        return;
+    }
+
+    let Some(level) = levels.get(handle) else {
+        return;
+    };
+
+    for entity in &layers {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    for (i, file) in level.layers.iter().enumerate() {
+        let style = config.layers.get(file).copied().unwrap_or_default();
+        if style.hidden {
+            continue;
+        }
+
+        let [r, g, b, a] = style.tint;
+        commands.spawn((
+            SpriteBundle {
+                texture: asset_server
+                    .load(format!("{}/{}/{file}", config.export_dir, level.identifier)),
+                sprite: Sprite {
+                    color: Color::srgba(r, g, b, a),
+                    ..Default::default()
+                },
+                // Later layers are drawn on top.
+                transform: Transform::from_xyz(0., 0., i as f32),
+                ..Default::default()
+            },
+            BackgroundLayer {
+                parallax: style.parallax,
+            },
+            RenderLayers::layer(BACKGROUND_LAYER),
+        ));
+    }
+
+    background.spawned = true;
+}
+
+/// Scales the layers like the level and lets them trail behind the camera by their
+/// parallax.
+fn apply_parallax(
+    mut layers: Query<(&mut Transform, &BackgroundLayer), Without<GameCamera>>,
+    cameras: Query<&Transform, With<GameCamera>>,
+    level_scale: Res<LevelScale>,
+) {
+    let camera = cameras
+        .iter()
+        .next()
+        .map_or(Vec2::ZERO, |transform| transform.translation.truncate());
+
+    for (mut transform, layer) in &mut layers {
+        let translation = camera * (1. - layer.parallax);
+        transform.translation.x = translation.x;
+        transform.translation.y = translation.y;
+        transform.scale = Vec3::new(level_scale.scale, level_scale.scale, 1.);
+    }
+}
+
+#[derive(Default)]
+struct BackgroundConfigLoader;
+
+#[derive(Default)]
+struct SimplifiedLevelLoader;
+
+#[derive(Debug)]
+pub enum BackgroundError {
+    Io(io::Error),
+    Ron(SpannedError),
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for BackgroundError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "could not read background: {err}"),
+            Self::Ron(err) => write!(f, "invalid background config: {err}"),
+            Self::Json(err) => write!(f, "invalid simplified export: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for BackgroundError {}
+
+impl AssetLoader for BackgroundConfigLoader {
+    type Asset = BackgroundConfig;
+    type Settings = ();
+    type Error = BackgroundError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<BackgroundConfig, BackgroundError> {
+        let mut bytes = Vec::new();
+        reader
+            .read_to_end(&mut bytes)
+            .await
+            .map_err(BackgroundError::Io)?;
+
+        ron::de::from_bytes(&bytes).map_err(BackgroundError::Ron)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["background.ron"]
+    }
+}
+
+impl AssetLoader for SimplifiedLevelLoader {
+    type Asset = SimplifiedLevel;
+    type Settings = ();
+    type Error = BackgroundError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<SimplifiedLevel, BackgroundError> {
+        let mut bytes = Vec::new();
+        reader
+            .read_to_end(&mut bytes)
+            .await
+            .map_err(BackgroundError::Io)?;
+
+        serde_json::from_slice(&bytes).map_err(BackgroundError::Json)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        // Every level's export is named `data.json`, so this doesn't claim other json.
+        &["data.json"]
+    }
+}
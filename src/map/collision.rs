@@ -0,0 +1,372 @@
+use super::selected_level_iid;
+use avian2d::{
+    collision::Collider,
+    dynamics::rigid_body::RigidBody,
+    math::{Scalar, Vector, PI},
+};
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use bevy_ecs_ldtk::prelude::*;
+
+pub struct CollisionPlugin;
+
+impl Plugin for CollisionPlugin {
+    fn build(&self, app: &mut App) {
+        let surfaces = Surface::ALL.map(|surface| surface as i32);
+        let ramps = CellShape::RAMPS.map(|shape| shape as i32);
+
+        for value in surfaces.into_iter().chain(ramps) {
+            app.register_ldtk_int_cell_for_layer::<ColliderBundle>("Collision", value);
+        }
+
+        // Ahead of the tick's gameplay systems and physics step, which wait for them.
+        app.init_resource::<SpawnedLevels>()
+            .add_systems(FixedFirst, (track_spawned_levels, spawn_level_colliders));
+    }
+}
+
+#[derive(Default, Clone, Bundle, LdtkIntCell)]
+pub struct ColliderBundle {
+    int_cell_collider: IntCellCollider,
+    #[from_int_grid_cell]
+    surface: Surface,
+    #[from_int_grid_cell]
+    shape: CellShape,
+    transform: Transform,
+}
+
+#[derive(Default, Clone, Component)]
+struct IntCellCollider;
+
+/// The material of a Collision cell, picked by its IntGrid value.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Surface {
+    #[default]
+    Solid = 1,
+    Ice = 2,
+    Sand = 3,
+    Bouncy = 4,
+}
+
+impl Surface {
+    const ALL: [Self; 4] = [Self::Solid, Self::Ice, Self::Sand, Self::Bouncy];
+
+    pub fn material(self) -> SurfaceMaterial {
+        match self {
+            Self::Solid => SurfaceMaterial::SOLID,
+            Self::Ice => SurfaceMaterial {
+                traction: 1.5,
+                ..SurfaceMaterial::SOLID
+            },
+            Self::Sand => SurfaceMaterial {
+                speed: 0.5,
+                jump: 0.8,
+                ..SurfaceMaterial::SOLID
+            },
+            Self::Bouncy => SurfaceMaterial {
+                bounce: 0.8,
+                ..SurfaceMaterial::SOLID
+            },
+        }
+    }
+}
+
+impl From<IntGridCell> for Surface {
+    fn from(cell: IntGridCell) -> Self {
+        Self::ALL
+            .into_iter()
+            .find(|surface| *surface as i32 == cell.value)
+            .unwrap_or_default()
+    }
+}
+
+/// The shape of a Collision cell, picked by its IntGrid value. Ramps are named after
+/// their angle and the side they rise towards.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CellShape {
+    #[default]
+    Square = 0,
+    Ramp45Right = 5,
+    Ramp45Left = 6,
+    Ramp22Right = 7,
+    Ramp22Left = 8,
+}
+
+impl CellShape {
+    const RAMPS: [Self; 4] = [
+        Self::Ramp45Right,
+        Self::Ramp45Left,
+        Self::Ramp22Right,
+        Self::Ramp22Left,
+    ];
+
+    /// Whether a row of these cells forms one continuous slope. A 22.5° cell only rises
+    /// part of the way to the next row, so a row of them is built as a single wedge
+    /// instead of a sawtooth. 45° cells rise a whole cell and line up along diagonals.
+    fn merges_along_row(self) -> bool {
+        matches!(self, Self::Ramp22Right | Self::Ramp22Left)
+    }
+
+    /// Builds the wedge collider of a run of `cells` ramp cells in a row, with its origin
+    /// at the run's bottom-left corner. Ramps rise by the tangent of their angle until
+    /// they reach the top of the row, the rest of a longer run is flat.
+    fn ramp_collider(self, grid_size: Scalar, cells: i32) -> Option<Collider> {
+        let (angle, rising_right) = match self {
+            Self::Square => return None,
+            Self::Ramp45Right => (PI / 4., true),
+            Self::Ramp45Left => (PI / 4., false),
+            Self::Ramp22Right => (PI / 8., true),
+            Self::Ramp22Left => (PI / 8., false),
+        };
+
+        let width = grid_size * cells as Scalar;
+        let rise = width * angle.tan();
+
+        let mut points = vec![
+            Vector::ZERO,
+            Vector::X * width,
+            Vector::new(width, rise.min(grid_size)),
+        ];
+        if rise > grid_size {
+            points.push(Vector::new(grid_size / angle.tan(), grid_size));
+        }
+        if !rising_right {
+            for point in &mut points {
+                point.x = width - point.x;
+            }
+        }
+
+        Collider::convex_hull(points)
+    }
+}
+
+impl From<IntGridCell> for CellShape {
+    fn from(cell: IntGridCell) -> Self {
+        Self::RAMPS
+            .into_iter()
+            .find(|shape| *shape as i32 == cell.value)
+            .unwrap_or_default()
+    }
+}
+
+/// How a [`Surface`] affects a character standing on it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SurfaceMaterial {
+    /// Multiplier for the walking speed.
+    pub speed: Scalar,
+    /// Multiplier for the jump impulse.
+    pub jump: Scalar,
+    /// How quickly the horizontal velocity reaches its target, per second.
+    /// Infinite traction stops and starts the character instantly.
+    pub traction: Scalar,
+    /// Fraction of the landing speed given back as an upward bounce.
+    pub bounce: Scalar,
+}
+
+impl SurfaceMaterial {
+    pub const SOLID: Self = Self {
+        speed: 1.,
+        jump: 1.,
+        traction: Scalar::INFINITY,
+        bounce: 0.,
+    };
+}
+
+/// Marks a static collider built from merged Collision cells.
+#[derive(Component)]
+pub struct LevelCollider;
+
+/// The levels that have finished spawning, whose Collision cells have been turned into
+/// colliders.
+#[derive(Resource, Debug, Default)]
+pub struct SpawnedLevels(pub HashSet<LevelIid>);
+
+/// Run condition for fixed-tick gameplay, which waits for the selected level to finish
+/// spawning. How many frames a level takes to spawn varies, so without it the same
+/// input could play out differently.
+pub fn level_spawned(
+    spawned: Res<SpawnedLevels>,
+    level_selection: Res<LevelSelection>,
+    projects: Query<&Handle<LdtkProject>>,
+    project_assets: Res<Assets<LdtkProject>>,
+) -> bool {
+    let project = projects
+        .get_single()
+        .ok()
+        .and_then(|handle| project_assets.get(handle));
+
+    selected_level_iid(project, &level_selection)
+        .is_some_and(|selected| spawned.0.iter().any(|iid| iid.as_str() == selected))
+}
+
+/// Levels announce they spawned in the same frame as their Collision cells, so by the
+/// time the next tick's gameplay runs their colliders have been built as well.
+fn track_spawned_levels(mut events: EventReader<LevelEvent>, mut spawned: ResMut<SpawnedLevels>) {
+    for event in events.read() {
+        match event {
+            LevelEvent::Spawned(iid) => {
+                spawned.0.insert(iid.clone());
+            }
+            LevelEvent::Despawned(iid) => {
+                spawned.0.remove(iid);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A rectangle of cells in grid coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct CellRect {
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+}
+
+/// Replaces freshly spawned Collision cells with a minimal set of merged static colliders,
+/// parented to the level so they are despawned along with it. Only square cells of the
+/// same [`Surface`] are merged together, ramps get a wedge per cell or per run of 22.5°
+/// cells.
+fn spawn_level_colliders(
+    mut commands: Commands,
+    cells: Query<(Entity, &GridCoords, &Surface, &CellShape, &Parent), Added<IntCellCollider>>,
+    layers: Query<(&Parent, &LayerMetadata)>,
+) {
+    let mut levels: HashMap<(Entity, Surface), (f32, HashSet<GridCoords>)> = HashMap::new();
+    let mut ramps: HashMap<(Entity, Surface, CellShape), (f32, HashSet<GridCoords>)> =
+        HashMap::new();
+
+    for (entity, coords, surface, shape, parent) in cells.iter() {
+        commands.entity(entity).despawn();
+
+        let Ok((level, layer)) = layers.get(parent.get()) else {
+            continue;
+        };
+
+        let grid_size = layer.grid_size as f32;
+
+        if *shape == CellShape::Square {
+            levels
+                .entry((level.get(), *surface))
+                .or_insert_with(|| (grid_size, HashSet::new()))
+                .1
+                .insert(*coords);
+        } else {
+            ramps
+                .entry((level.get(), *surface, *shape))
+                .or_insert_with(|| (grid_size, HashSet::new()))
+                .1
+                .insert(*coords);
+        }
+    }
+
+    for ((level, surface, shape), (grid_size, cells)) in ramps {
+        commands.entity(level).with_children(|level| {
+            for rect in merge_rows(&cells, shape.merges_along_row()) {
+                let Some(collider) = shape.ramp_collider(grid_size, rect.width) else {
+                    continue;
+                };
+                let corner = Vec2::new(rect.x as f32, rect.y as f32) * grid_size;
+
+                level.spawn((
+                    LevelCollider,
+                    surface,
+                    RigidBody::Static,
+                    collider,
+                    TransformBundle::from_transform(Transform::from_translation(
+                        corner.extend(0.0),
+                    )),
+                ));
+            }
+        });
+    }
+
+    for ((level, surface), (grid_size, cells)) in levels {
+        commands.entity(level).with_children(|level| {
+            for rect in merge_cells(&cells) {
+                let size = Vec2::new(rect.width as f32, rect.height as f32) * grid_size;
+                let corner = Vec2::new(rect.x as f32, rect.y as f32) * grid_size;
+
+                level.spawn((
+                    LevelCollider,
+                    surface,
+                    RigidBody::Static,
+                    Collider::rectangle(size.x, size.y),
+                    TransformBundle::from_transform(Transform::from_translation(
+                        (corner + size / 2.0).extend(0.0),
+                    )),
+                ));
+            }
+        });
+    }
+}
+
+/// Greedily merges cells into rectangles, first growing each rectangle along its row
+/// and then upwards for as long as the whole span is filled.
+fn merge_cells(cells: &HashSet<GridCoords>) -> Vec<CellRect> {
+    let mut remaining = cells.clone();
+    let mut ordered: Vec<GridCoords> = cells.iter().copied().collect();
+    ordered.sort_by_key(|coords| (coords.y, coords.x));
+
+    let mut rects = Vec::new();
+
+    for start in ordered {
+        if !remaining.contains(&start) {
+            continue;
+        }
+
+        let mut width = 1;
+        while remaining.contains(&GridCoords::new(start.x + width, start.y)) {
+            width += 1;
+        }
+
+        let mut height = 1;
+        while (0..width)
+            .all(|dx| remaining.contains(&GridCoords::new(start.x + dx, start.y + height)))
+        {
+            height += 1;
+        }
+
+        for dy in 0..height {
+            for dx in 0..width {
+                remaining.remove(&GridCoords::new(start.x + dx, start.y + dy));
+            }
+        }
+
+        rects.push(CellRect {
+            x: start.x,
+            y: start.y,
+            width,
+            height,
+        });
+    }
+
+    rects
+}
+
+/// Splits cells into runs along their rows, or into single cells unless `merge` is set.
+fn merge_rows(cells: &HashSet<GridCoords>, merge: bool) -> Vec<CellRect> {
+    let mut ordered: Vec<GridCoords> = cells.iter().copied().collect();
+    ordered.sort_by_key(|coords| (coords.y, coords.x));
+
+    let mut rects: Vec<CellRect> = Vec::new();
+
+    for coords in ordered {
+        match rects.last_mut() {
+            Some(run) if merge && run.y == coords.y && run.x + run.width == coords.x => {
+                run.width += 1;
+            }
+            _ => rects.push(CellRect {
+                x: coords.x,
+                y: coords.y,
+                width: 1,
+                height: 1,
+            }),
+        }
+    }
+
+    rects
+}
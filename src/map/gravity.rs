@@ -0,0 +1,107 @@
+use super::collision::level_spawned;
+use crate::{player::Player, state::GameState};
+use avian2d::prelude::*;
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+
+pub struct GravityZonePlugin;
+
+impl Plugin for GravityZonePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<GravityZoneBundle>("GravityZone")
+            .add_systems(
+                FixedPreUpdate,
+                apply_gravity_zones
+                    .before(PhysicsSet::Prepare)
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(level_spawned),
+            );
+    }
+}
+
+#[derive(Default, Bundle, LdtkEntity)]
+struct GravityZoneBundle {
+    #[from_entity_instance]
+    zone: GravityZone,
+    #[from_entity_instance]
+    sensor: GravityZoneSensorBundle,
+}
+
+/// Changes how the player falls inside it, for low gravity or underwater sections.
+/// Only the player's own [`GravityScale`] and [`LinearDamping`] change, the global
+/// [`Gravity`] stays as it is.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct GravityZone {
+    pub gravity_scale: f32,
+    pub damping: f32,
+}
+
+impl Default for GravityZone {
+    fn default() -> Self {
+        Self {
+            gravity_scale: 1.,
+            damping: 0.,
+        }
+    }
+}
+
+impl From<&EntityInstance> for GravityZone {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        let default = Self::default();
+
+        Self {
+            gravity_scale: entity_instance
+                .get_float_field("GravityScale")
+                .copied()
+                .unwrap_or(default.gravity_scale),
+            damping: entity_instance
+                .get_float_field("Damping")
+                .copied()
+                .unwrap_or(default.damping),
+        }
+    }
+}
+
+#[derive(Default, Bundle)]
+struct GravityZoneSensorBundle {
+    rigid_body: RigidBody,
+    collider: Collider,
+    sensor: Sensor,
+    colliding_entities: CollidingEntities,
+}
+
+impl From<&EntityInstance> for GravityZoneSensorBundle {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        Self {
+            rigid_body: RigidBody::Static,
+            collider: Collider::rectangle(
+                entity_instance.width as f32,
+                entity_instance.height as f32,
+            ),
+            sensor: Sensor,
+            colliding_entities: CollidingEntities::default(),
+        }
+    }
+}
+
+/// Sets the player's gravity scale and damping from the zones it is in. Overlapping
+/// zones multiply their gravity and add up their damping.
+fn apply_gravity_zones(
+    zones: Query<(&GravityZone, &CollidingEntities)>,
+    mut player: Query<(Entity, &mut GravityScale, &mut LinearDamping), With<Player>>,
+) {
+    let Ok((entity, mut gravity_scale, mut damping)) = player.get_single_mut() else {
+        return;
+    };
+
+    let mut combined = GravityZone::default();
+    for (zone, colliding) in &zones {
+        if colliding.contains(&entity) {
+            combined.gravity_scale *= zone.gravity_scale;
+            combined.damping += zone.damping;
+        }
+    }
+
+    gravity_scale.set_if_neq(GravityScale(combined.gravity_scale));
+    damping.set_if_neq(LinearDamping(combined.damping));
+}
@@ -0,0 +1,295 @@
+use super::selected_level;
+use crate::{
+    effects::{PlayEffect, DEATH_EFFECT},
+    menu::NewGame,
+    player::{fall::FallTracker, movement::Stunned, JuiceMeter, Player},
+    run::Difficulty,
+    state::GameState,
+};
+use avian2d::prelude::*;
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub struct HazardPlugin;
+
+impl Plugin for HazardPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<HazardBundle>("Hazard")
+            .register_ldtk_entity::<CheckpointBundle>("Checkpoint")
+            .add_event::<PlayerDied>()
+            .init_resource::<RespawnPoint>()
+            .init_resource::<ClimbStart>()
+            .add_systems(
+                Update,
+                (clear_respawn_point, remember_start, show_checkpoints).chain(),
+            )
+            .add_systems(
+                FixedPostUpdate,
+                (activate_checkpoints, hit_hazards)
+                    .chain()
+                    .after(PhysicsSet::Sync)
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+#[derive(Default, Bundle, LdtkEntity)]
+struct HazardBundle {
+    #[with(hazard_sprite)]
+    sprite: Sprite,
+    texture: Handle<Image>,
+    #[from_entity_instance]
+    sensor: SensorBundle,
+    hazard: Hazard,
+}
+
+/// Spikes, lava and anything else that sends the player back to the [`RespawnPoint`].
+#[derive(Component, Default)]
+pub struct Hazard;
+
+#[derive(Default, Bundle, LdtkEntity)]
+struct CheckpointBundle {
+    #[with(checkpoint_sprite)]
+    sprite: Sprite,
+    texture: Handle<Image>,
+    #[from_entity_instance]
+    sensor: SensorBundle,
+    checkpoint: Checkpoint,
+}
+
+/// Becomes the [`RespawnPoint`] when touched, unless the [`Difficulty`] has none.
+#[derive(Component, Default)]
+pub struct Checkpoint;
+
+fn hazard_sprite(entity_instance: &EntityInstance) -> Sprite {
+    entity_sprite(entity_instance, HAZARD_COLOR)
+}
+
+fn checkpoint_sprite(entity_instance: &EntityInstance) -> Sprite {
+    entity_sprite(entity_instance, CHECKPOINT_COLOR)
+}
+
+fn entity_sprite(entity_instance: &EntityInstance, color: Color) -> Sprite {
+    Sprite {
+        color,
+        custom_size: Some(Vec2::new(
+            entity_instance.width as f32,
+            entity_instance.height as f32,
+        )),
+        ..Default::default()
+    }
+}
+
+const HAZARD_COLOR: Color = Color::srgb(0.89, 0.23, 0.27);
+const CHECKPOINT_COLOR: Color = Color::srgb(0.5, 0.45, 0.2);
+const CHECKPOINT_ACTIVE_COLOR: Color = Color::srgb(1., 0.9, 0.38);
+
+#[derive(Default, Bundle)]
+struct SensorBundle {
+    rigid_body: RigidBody,
+    collider: Collider,
+    sensor: Sensor,
+}
+
+impl From<&EntityInstance> for SensorBundle {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        Self {
+            rigid_body: RigidBody::Static,
+            collider: Collider::rectangle(
+                entity_instance.width as f32,
+                entity_instance.height as f32,
+            ),
+            sensor: Sensor,
+        }
+    }
+}
+
+/// Sent when the player touches a [`Hazard`], before it is moved to the [`RespawnPoint`].
+#[derive(Event, Debug)]
+pub struct PlayerDied;
+
+/// Where the player goes after touching a [`Hazard`]: the last activated checkpoint, or
+/// the [`ClimbStart`] if there is none.
+#[derive(Resource, Debug, Default)]
+pub struct RespawnPoint(pub Option<Respawn>);
+
+/// Where the player entity is placed in the map, which every new climb starts from.
+#[derive(Resource, Debug, Default)]
+pub struct ClimbStart(pub Option<Respawn>);
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Respawn {
+    pub level_iid: String,
+    /// Translation relative to the LDtk world.
+    pub translation: [f32; 2],
+    /// The checkpoint's entity iid, or `None` for the start of the climb.
+    pub checkpoint: Option<String>,
+}
+
+fn clear_respawn_point(mut new_game: EventReader<NewGame>, mut respawn: ResMut<RespawnPoint>) {
+    if new_game.read().count() > 0 {
+        respawn.0 = None;
+    }
+}
+
+/// Remembers the player entity's placement as it spawns, before a resumed save moves it.
+/// The level is looked up by the entity, as the selected level may already be the save's.
+fn remember_start(
+    mut start: ResMut<ClimbStart>,
+    player: Query<(&Transform, &EntityIid), Added<Player>>,
+    projects: Query<&Handle<LdtkProject>>,
+    project_assets: Res<Assets<LdtkProject>>,
+) {
+    let Ok((transform, iid)) = player.get_single() else {
+        return;
+    };
+
+    let Some(level) = projects
+        .get_single()
+        .ok()
+        .and_then(|handle| project_assets.get(handle))
+        .and_then(|project| {
+            project.iter_raw_levels().find(|level| {
+                level
+                    .layer_instances
+                    .iter()
+                    .flatten()
+                    .flat_map(|layer| &layer.entity_instances)
+                    .any(|entity| entity.iid == iid.as_str())
+            })
+        })
+    else {
+        return;
+    };
+
+    start.0 = Some(Respawn {
+        level_iid: level.iid.clone(),
+        translation: transform.translation.truncate().to_array(),
+        checkpoint: None,
+    });
+}
+
+fn activate_checkpoints(
+    mut collisions: EventReader<CollisionStarted>,
+    mut respawn: ResMut<RespawnPoint>,
+    checkpoints: Query<(&Transform, &EntityIid), With<Checkpoint>>,
+    player: Query<(), With<Player>>,
+    difficulty: Res<Difficulty>,
+    level_selection: Res<LevelSelection>,
+    projects: Query<&Handle<LdtkProject>>,
+    project_assets: Res<Assets<LdtkProject>>,
+) {
+    if !difficulty.has_checkpoints() {
+        collisions.clear();
+        return;
+    }
+
+    for CollisionStarted(a, b) in collisions.read() {
+        let checkpoint = if player.contains(*a) {
+            checkpoints.get(*b)
+        } else if player.contains(*b) {
+            checkpoints.get(*a)
+        } else {
+            continue;
+        };
+
+        let Ok((transform, iid)) = checkpoint else {
+            continue;
+        };
+
+        let Some(level) = projects
+            .get_single()
+            .ok()
+            .and_then(|handle| project_assets.get(handle))
+            .and_then(|project| selected_level(project, &level_selection))
+        else {
+            continue;
+        };
+
+        respawn.0 = Some(Respawn {
+            level_iid: level.iid.clone(),
+            translation: transform.translation.truncate().to_array(),
+            checkpoint: Some(iid.as_str().to_owned()),
+        });
+    }
+}
+
+fn hit_hazards(
+    mut commands: Commands,
+    mut collisions: EventReader<CollisionStarted>,
+    mut died: EventWriter<PlayerDied>,
+    mut play: EventWriter<PlayEffect>,
+    hazards: Query<(), With<Hazard>>,
+    mut player: Query<
+        (
+            Entity,
+            &mut Transform,
+            &mut LinearVelocity,
+            &mut JuiceMeter,
+            &mut FallTracker,
+        ),
+        With<Player>,
+    >,
+    respawn: Res<RespawnPoint>,
+    start: Res<ClimbStart>,
+    mut level_selection: ResMut<LevelSelection>,
+) {
+    let Ok((entity, mut transform, mut velocity, mut juice, mut fall)) = player.get_single_mut()
+    else {
+        return;
+    };
+
+    let hit = collisions.read().any(|CollisionStarted(a, b)| {
+        (*a == entity && hazards.contains(*b)) || (*b == entity && hazards.contains(*a))
+    });
+    if !hit {
+        return;
+    }
+
+    died.send(PlayerDied);
+    play.send(PlayEffect::new(
+        DEATH_EFFECT,
+        transform.translation.truncate(),
+    ));
+
+    let Some(respawn) = respawn.0.as_ref().or(start.0.as_ref()) else {
+        return;
+    };
+
+    // Like restoring a save, the player is worldly and keeps existing if the respawn
+    // point is in another level.
+    transform.translation = Vec2::from_array(respawn.translation).extend(transform.translation.z);
+    *velocity = LinearVelocity::ZERO;
+    *juice = JuiceMeter::Idle;
+    *fall = FallTracker::default();
+    commands.entity(entity).remove::<Stunned>();
+    level_selection.set_if_neq(LevelSelection::iid(respawn.level_iid.clone()));
+}
+
+/// Hides checkpoints when the [`Difficulty`] has none and lights up the active one.
+fn show_checkpoints(
+    mut checkpoints: Query<(&EntityIid, &mut Sprite, &mut Visibility), With<Checkpoint>>,
+    difficulty: Res<Difficulty>,
+    respawn: Res<RespawnPoint>,
+) {
+    let visibility = if difficulty.has_checkpoints() {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+    let active = respawn
+        .0
+        .as_ref()
+        .and_then(|respawn| respawn.checkpoint.as_deref());
+
+    for (iid, mut sprite, mut checkpoint_visibility) in &mut checkpoints {
+        checkpoint_visibility.set_if_neq(visibility);
+
+        sprite.color = if Some(iid.as_str()) == active {
+            CHECKPOINT_ACTIVE_COLOR
+        } else {
+            CHECKPOINT_COLOR
+        };
+    }
+}
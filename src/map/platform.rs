@@ -0,0 +1,183 @@
+use super::{collision::level_spawned, LevelScale};
+use crate::state::GameState;
+use avian2d::prelude::*;
+use bevy::prelude::*;
+use bevy_ecs_ldtk::{
+    ldtk::{LayerInstance, TilesetDefinition},
+    prelude::*,
+    utils::ldtk_pixel_coords_to_translation_pivoted,
+};
+
+pub struct PlatformPlugin;
+
+impl Plugin for PlatformPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<PlatformBundle>("Platform")
+            .add_systems(
+                FixedPreUpdate,
+                move_platforms
+                    .before(PhysicsSet::Prepare)
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(level_spawned),
+            );
+    }
+}
+
+#[derive(Default, Bundle, LdtkEntity)]
+struct PlatformBundle {
+    #[with(platform_sprite)]
+    sprite: Sprite,
+    texture: Handle<Image>,
+    #[from_entity_instance]
+    body: PlatformBodyBundle,
+    #[ldtk_entity]
+    motion: PlatformMotion,
+}
+
+fn platform_sprite(entity_instance: &EntityInstance) -> Sprite {
+    Sprite {
+        color: Color::srgb(0.66, 0.71, 0.7),
+        custom_size: Some(Vec2::new(
+            entity_instance.width as f32,
+            entity_instance.height as f32,
+        )),
+        ..Default::default()
+    }
+}
+
+#[derive(Default, Bundle)]
+struct PlatformBodyBundle {
+    rigid_body: RigidBody,
+    collider: Collider,
+}
+
+impl From<&EntityInstance> for PlatformBodyBundle {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        Self {
+            rigid_body: RigidBody::Kinematic,
+            collider: Collider::rectangle(
+                entity_instance.width as f32,
+                entity_instance.height as f32,
+            ),
+        }
+    }
+}
+
+/// Moves a kinematic platform back and forth along a path, resting at both ends.
+#[derive(Component, Debug, Default)]
+pub struct PlatformMotion {
+    /// Level space translations, starting with where the platform was placed.
+    path: Vec<Vec2>,
+    /// LDtk pixels per second.
+    speed: f32,
+    rest: Timer,
+    target: usize,
+    forward: bool,
+}
+
+impl PlatformMotion {
+    /// Picks the next point on the path, turning around at either end.
+    fn advance(&mut self) {
+        let last = self.path.len() - 1;
+        if self.target == last {
+            self.forward = false;
+        } else if self.target == 0 {
+            self.forward = true;
+        }
+
+        let is_end = self.target == 0 || self.target == last;
+        if is_end {
+            self.rest.reset();
+        }
+
+        self.target = if self.forward {
+            self.target + 1
+        } else {
+            self.target - 1
+        };
+    }
+}
+
+impl LdtkEntity for PlatformMotion {
+    fn bundle_entity(
+        entity_instance: &EntityInstance,
+        layer_instance: &LayerInstance,
+        _: Option<&Handle<Image>>,
+        _: Option<&TilesetDefinition>,
+        _: &AssetServer,
+        _: &mut Assets<TextureAtlasLayout>,
+    ) -> Self {
+        let size = IVec2::new(entity_instance.width, entity_instance.height);
+        let level_height = layer_instance.c_hei * layer_instance.grid_size;
+        let grid_size = layer_instance.grid_size as f32;
+
+        let mut path = vec![ldtk_pixel_coords_to_translation_pivoted(
+            entity_instance.px,
+            level_height,
+            size,
+            entity_instance.pivot,
+        )];
+
+        // Path points are cells, the platform is centred on them.
+        let points = entity_instance
+            .get_maybe_points_field("Path")
+            .into_iter()
+            .flatten()
+            .flatten();
+        for point in points {
+            // LDtk cells count down from the top of the level.
+            let cell = Vec2::new(point.x as f32, (layer_instance.c_hei - point.y) as f32 - 1.);
+            path.push((cell + Vec2::splat(0.5)) * grid_size);
+        }
+
+        let wait = entity_instance
+            .get_float_field("Wait")
+            .copied()
+            .unwrap_or_default()
+            .max(0.);
+
+        let mut rest = Timer::from_seconds(wait, TimerMode::Once);
+        rest.tick(rest.duration());
+
+        Self {
+            path,
+            speed: entity_instance
+                .get_float_field("Speed")
+                .copied()
+                .unwrap_or_default(),
+            rest,
+            target: 0,
+            forward: true,
+        }
+    }
+}
+
+/// Steers platforms with their velocity, so that the solver and riders see them move.
+fn move_platforms(
+    mut platforms: Query<(&Transform, &mut PlatformMotion, &mut LinearVelocity)>,
+    level_scale: Res<LevelScale>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_seconds();
+    if dt <= 0. {
+        return;
+    }
+
+    for (transform, mut motion, mut velocity) in &mut platforms {
+        if motion.path.len() < 2 || !motion.rest.tick(time.delta()).finished() {
+            velocity.0 = Vec2::ZERO;
+            continue;
+        }
+
+        let to_target = motion.path[motion.target] - transform.translation.truncate();
+        let step = motion.speed * dt;
+
+        // Velocity is in world units, the path in level space.
+        velocity.0 = if to_target.length() <= step {
+            motion.advance();
+            to_target / dt * level_scale.scale
+        } else {
+            to_target.normalize() * motion.speed * level_scale.scale
+        };
+    }
+}
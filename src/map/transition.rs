@@ -0,0 +1,93 @@
+use super::selected_level;
+use crate::{player::Player, state::GameState};
+use avian2d::prelude::PhysicsSet;
+use bevy::prelude::*;
+use bevy_ecs_ldtk::{ldtk::Level, prelude::*};
+
+pub struct LevelTransitionPlugin;
+
+impl Plugin for LevelTransitionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<LevelTransition>().add_systems(
+            FixedPostUpdate,
+            level_transition
+                .after(PhysicsSet::Sync)
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+/// Sent when the player crosses into a neighbouring level.
+#[derive(Event, Debug, Clone)]
+pub struct LevelTransition {
+    pub from: LevelIid,
+    pub to: LevelIid,
+    /// Whether the player climbed out of the top of the previous level.
+    pub upwards: bool,
+}
+
+/// Switches the [`LevelSelection`] to the neighbouring level when the player leaves
+/// the top or bottom edge of the current one.
+///
+/// The player is [`Worldly`], so it survives the level despawn along with its velocity
+/// and [`JuiceMeter`](crate::player::JuiceMeter). Only its translation has to be moved
+/// into the new level's space.
+pub fn level_transition(
+    mut player: Query<&mut Transform, With<Player>>,
+    mut level_selection: ResMut<LevelSelection>,
+    mut transitions: EventWriter<LevelTransition>,
+    projects: Query<&Handle<LdtkProject>>,
+    project_assets: Res<Assets<LdtkProject>>,
+) {
+    let Ok(mut transform) = player.get_single_mut() else {
+        return;
+    };
+
+    let Some(project) = projects
+        .get_single()
+        .ok()
+        .and_then(|handle| project_assets.get(handle))
+    else {
+        return;
+    };
+
+    let Some(current) = selected_level(project, &level_selection) else {
+        return;
+    };
+
+    let y = transform.translation.y;
+    if (0.0..=current.px_hei as f32).contains(&y) {
+        return;
+    }
+
+    // LDtk world space has y pointing down, level space has it pointing up.
+    let world_position = Vec2::new(
+        current.world_x as f32 + transform.translation.x,
+        (current.world_y + current.px_hei) as f32 - y,
+    );
+
+    let Some(next) = project
+        .iter_raw_levels()
+        .find(|level| level.iid != current.iid && contains(level, world_position))
+    else {
+        return;
+    };
+
+    transform.translation.x = world_position.x - next.world_x as f32;
+    transform.translation.y = (next.world_y + next.px_hei) as f32 - world_position.y;
+
+    *level_selection = LevelSelection::iid(next.iid.clone());
+
+    transitions.send(LevelTransition {
+        from: LevelIid::new(current.iid.clone()),
+        to: LevelIid::new(next.iid.clone()),
+        upwards: y > 0.,
+    });
+}
+
+fn contains(level: &Level, world_position: Vec2) -> bool {
+    let min = Vec2::new(level.world_x as f32, level.world_y as f32);
+    let max = min + Vec2::new(level.px_wid as f32, level.px_hei as f32);
+
+    world_position.cmpge(min).all() && world_position.cmplt(max).all()
+}
@@ -0,0 +1,229 @@
+use super::collision::level_spawned;
+use crate::{
+    effects::{EffectRegistry, GUST_EFFECT},
+    menu::{ContinueGame, NewGame},
+    player::{movement::Grounded, Player},
+    save::PendingRestore,
+    state::GameState,
+};
+use avian2d::prelude::*;
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_hanabi::prelude::*;
+
+pub struct WindPlugin;
+
+impl Plugin for WindPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<WindZoneBundle>("WindZone")
+            .init_resource::<WindClock>()
+            .add_systems(Update, (reset_wind_clock, spawn_gusts, toggle_gusts))
+            .add_systems(
+                FixedPreUpdate,
+                (tick_wind_clock, apply_wind)
+                    .chain()
+                    .before(PhysicsSet::Prepare)
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(not(resource_exists::<PendingRestore>))
+                    .run_if(level_spawned),
+            );
+    }
+}
+
+#[derive(Default, Bundle, LdtkEntity)]
+struct WindZoneBundle {
+    #[from_entity_instance]
+    wind: WindZone,
+    #[from_entity_instance]
+    sensor: WindSensorBundle,
+}
+
+/// Pushes airborne players inside it, optionally in gusts.
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct WindZone {
+    /// Unit vector the wind blows towards.
+    pub direction: Vec2,
+    /// Acceleration, in the same units as [`Gravity`].
+    pub strength: f32,
+    /// Size of the zone in LDtk pixels.
+    pub size: Vec2,
+    /// Seconds on and off per gust, or `None` for constant wind.
+    pub cycle: Option<(f32, f32)>,
+}
+
+impl WindZone {
+    /// Whether the wind blows `time` seconds into the [`WindClock`]. Gusts follow the
+    /// clock so they keep their rhythm across level changes.
+    pub fn is_blowing(&self, time: f32) -> bool {
+        match self.cycle {
+            None => true,
+            Some((on, off)) if on + off <= 0. => true,
+            Some((on, off)) => time % (on + off) < on,
+        }
+    }
+}
+
+impl From<&EntityInstance> for WindZone {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        let direction = entity_instance
+            .get_float_field("Direction")
+            .copied()
+            .unwrap_or_default()
+            .to_radians();
+        let strength = entity_instance
+            .get_float_field("Strength")
+            .copied()
+            .unwrap_or_default();
+
+        let on = entity_instance.get_maybe_float_field("OnSeconds").ok();
+        let off = entity_instance.get_maybe_float_field("OffSeconds").ok();
+        let cycle = match (on.copied().flatten(), off.copied().flatten()) {
+            (Some(on), off) => Some((on, off.unwrap_or_default())),
+            _ => None,
+        };
+
+        Self {
+            direction: Vec2::from_angle(direction),
+            strength,
+            size: Vec2::new(entity_instance.width as f32, entity_instance.height as f32),
+            cycle,
+        }
+    }
+}
+
+#[derive(Default, Bundle)]
+struct WindSensorBundle {
+    rigid_body: RigidBody,
+    collider: Collider,
+    sensor: Sensor,
+    colliding_entities: CollidingEntities,
+}
+
+impl From<&EntityInstance> for WindSensorBundle {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        Self {
+            rigid_body: RigidBody::Static,
+            collider: Collider::rectangle(
+                entity_instance.width as f32,
+                entity_instance.height as f32,
+            ),
+            sensor: Sensor,
+            colliding_entities: CollidingEntities::default(),
+        }
+    }
+}
+
+/// Counts the fixed ticks simulated since the climb started or was continued, so gusts
+/// blow on the same ticks in a replay as they did live. The run timer advances per
+/// frame and can't be used for that.
+#[derive(Resource, Debug, Default)]
+pub struct WindClock {
+    ticks: u64,
+}
+
+impl WindClock {
+    pub fn seconds(&self, time: &Time<Fixed>) -> f32 {
+        self.ticks as f32 * time.timestep().as_secs_f32()
+    }
+}
+
+fn reset_wind_clock(
+    mut new_game: EventReader<NewGame>,
+    mut continue_game: EventReader<ContinueGame>,
+    mut clock: ResMut<WindClock>,
+) {
+    if new_game.read().count() + continue_game.read().count() > 0 {
+        *clock = WindClock::default();
+    }
+}
+
+fn tick_wind_clock(mut clock: ResMut<WindClock>) {
+    clock.ticks += 1;
+}
+
+/// Accelerates players that are inside a blowing [`WindZone`] and not [`Grounded`].
+fn apply_wind(
+    zones: Query<(&WindZone, &CollidingEntities)>,
+    mut players: Query<&mut LinearVelocity, (With<Player>, Without<Grounded>)>,
+    clock: Res<WindClock>,
+    fixed_time: Res<Time<Fixed>>,
+    time: Res<Time>,
+) {
+    let wind_time = clock.seconds(&fixed_time);
+
+    for (zone, colliding) in &zones {
+        if !zone.is_blowing(wind_time) {
+            continue;
+        }
+
+        for &entity in colliding.iter() {
+            if let Ok(mut velocity) = players.get_mut(entity) {
+                velocity.0 += zone.direction * zone.strength * time.delta_seconds();
+            }
+        }
+    }
+}
+
+/// Speed of the gust particles relative to the zone's strength.
+const GUST_PARTICLE_SPEED: f32 = 0.5;
+
+/// Marks wind zones whose gust particles have been spawned.
+#[derive(Component)]
+struct GustsStarted;
+
+/// Loops the gust effect across each wind zone, like an emitter placed over the zone.
+fn spawn_gusts(
+    mut commands: Commands,
+    zones: Query<(Entity, &WindZone), Without<GustsStarted>>,
+    registry: Res<EffectRegistry>,
+) {
+    // Wait for the library, the zones may spawn before it has loaded.
+    let Some(effect) = registry.get(GUST_EFFECT) else {
+        return;
+    };
+
+    for (entity, zone) in &zones {
+        let mut properties = EffectProperties::default();
+        properties.set(
+            "velocity",
+            (zone.direction * zone.strength * GUST_PARTICLE_SPEED)
+                .extend(0.)
+                .into(),
+        );
+        properties.set("extent", zone.size.extend(0.).into());
+
+        commands
+            .entity(entity)
+            .insert(GustsStarted)
+            .with_children(|zone| {
+                zone.spawn((
+                    ParticleEffectBundle {
+                        effect: ParticleEffect::new(effect.clone()),
+                        ..Default::default()
+                    },
+                    properties,
+                ));
+            });
+    }
+}
+
+/// Stops the particles between gusts.
+fn toggle_gusts(
+    zones: Query<(&WindZone, &Children)>,
+    mut spawners: Query<&mut EffectSpawner>,
+    clock: Res<WindClock>,
+    fixed_time: Res<Time<Fixed>>,
+) {
+    let wind_time = clock.seconds(&fixed_time);
+
+    for (zone, children) in &zones {
+        let blowing = zone.is_blowing(wind_time);
+
+        let mut effects = spawners.iter_many_mut(children);
+        while let Some(mut spawner) = effects.fetch_next() {
+            if spawner.is_active() != blowing {
+                spawner.set_active(blowing);
+            }
+        }
+    }
+}
@@ -0,0 +1,257 @@
+use crate::{
+    camera::CameraMode, effects::PlayerParticles, hud::HudSettings,
+    player::jump_profile::ChargeMode, run::Difficulty, save::LoadedSave, state::GameState,
+};
+use bevy::prelude::*;
+
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<NewGame>()
+            .add_event::<ContinueGame>()
+            .add_systems(OnEnter(GameState::Title), spawn_title_menu)
+            .add_systems(OnEnter(GameState::Paused), spawn_pause_menu)
+            .add_systems(Update, (toggle_pause, press_menu_buttons));
+    }
+}
+
+/// Sent when a fresh climb is started from the title menu.
+#[derive(Event, Debug)]
+pub struct NewGame;
+
+/// Sent when the saved climb is resumed from the title menu.
+#[derive(Event, Debug)]
+pub struct ContinueGame;
+
+#[derive(Component, Debug, Clone, Copy)]
+pub enum MenuButton {
+    Continue,
+    NewGame,
+    ChargeMode,
+    Difficulty,
+    Resume,
+    ChargeMeter,
+    CameraMode,
+    PlayerParticles,
+    Title,
+    Quit,
+}
+
+const BUTTON_COLOR: Color = Color::srgb(0.15, 0.15, 0.15);
+const BUTTON_HOVERED_COLOR: Color = Color::srgb(0.3, 0.3, 0.3);
+
+fn spawn_title_menu(
+    commands: Commands,
+    loaded: Res<LoadedSave>,
+    mode: Res<ChargeMode>,
+    difficulty: Res<Difficulty>,
+    particles: Res<PlayerParticles>,
+) {
+    let mut buttons = Vec::new();
+    if loaded.save.is_some() {
+        buttons.push((MenuButton::Continue, "Continue"));
+    }
+    buttons.push((MenuButton::NewGame, "New Game"));
+    buttons.push((MenuButton::PlayerParticles, particles.label()));
+    // Only apply to new games, a continued climb keeps the modes it was saved with.
+    buttons.push((MenuButton::ChargeMode, mode.label()));
+    buttons.push((MenuButton::Difficulty, difficulty.label()));
+    buttons.push((MenuButton::Quit, "Quit"));
+
+    spawn_menu(
+        commands,
+        GameState::Title,
+        "jump wiz",
+        loaded.problem.as_deref(),
+        &buttons,
+    );
+}
+
+fn spawn_pause_menu(
+    commands: Commands,
+    hud: Res<HudSettings>,
+    camera: Res<CameraMode>,
+    particles: Res<PlayerParticles>,
+) {
+    spawn_menu(
+        commands,
+        GameState::Paused,
+        "Paused",
+        None,
+        &[
+            (MenuButton::Resume, "Resume"),
+            (MenuButton::ChargeMeter, hud.charge_meter.label()),
+            (MenuButton::CameraMode, camera.label()),
+            (MenuButton::PlayerParticles, particles.label()),
+            (MenuButton::Quit, "Quit"),
+        ],
+    );
+}
+
+/// Spawns a centred column with a title, an optional warning and buttons, despawned when
+/// leaving `state`.
+fn spawn_menu(
+    mut commands: Commands,
+    state: GameState,
+    title: &str,
+    warning: Option<&str>,
+    buttons: &[(MenuButton, &str)],
+) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(16.),
+                    ..Default::default()
+                },
+                background_color: BackgroundColor(Color::srgba(0., 0., 0., 0.6)),
+                ..Default::default()
+            },
+            StateScoped(state),
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                title,
+                TextStyle {
+                    font_size: 72.,
+                    color: Color::WHITE,
+                    ..Default::default()
+                },
+            ));
+
+            if let Some(warning) = warning {
+                parent.spawn(TextBundle::from_section(
+                    warning,
+                    TextStyle {
+                        font_size: 24.,
+                        color: Color::srgb(1., 0.4, 0.4),
+                        ..Default::default()
+                    },
+                ));
+            }
+
+            for (button, label) in buttons {
+                spawn_button(parent, *button, label);
+            }
+        });
+}
+
+/// Spawns a button handled by [`MenuPlugin`], for screens built outside this module.
+pub fn spawn_button(parent: &mut ChildBuilder, button: MenuButton, label: &str) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    width: Val::Px(240.),
+                    height: Val::Px(64.),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..Default::default()
+                },
+                background_color: BackgroundColor(BUTTON_COLOR),
+                ..Default::default()
+            },
+            button,
+        ))
+        .with_children(|button| {
+            button.spawn(TextBundle::from_section(
+                label,
+                TextStyle {
+                    font_size: 40.,
+                    color: Color::WHITE,
+                    ..Default::default()
+                },
+            ));
+        });
+}
+
+/// Escape pauses a running game and resumes a paused one, it no longer quits. After the
+/// goal it goes back to the title.
+fn toggle_pause(
+    keys: Res<ButtonInput<KeyCode>>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if !keys.just_pressed(KeyCode::Escape) {
+        return;
+    }
+
+    match state.get() {
+        GameState::Playing => next_state.set(GameState::Paused),
+        GameState::Paused => next_state.set(GameState::Playing),
+        GameState::Victory => next_state.set(GameState::Title),
+        GameState::Title => {}
+    }
+}
+
+fn press_menu_buttons(
+    mut buttons: Query<
+        (&Interaction, &MenuButton, &mut BackgroundColor, &Children),
+        Changed<Interaction>,
+    >,
+    mut labels: Query<&mut Text>,
+    mut hud: ResMut<HudSettings>,
+    mut camera: ResMut<CameraMode>,
+    mut particles: ResMut<PlayerParticles>,
+    mut mode: ResMut<ChargeMode>,
+    mut difficulty: ResMut<Difficulty>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut new_game: EventWriter<NewGame>,
+    mut continue_game: EventWriter<ContinueGame>,
+    mut exit: EventWriter<AppExit>,
+) {
+    for (interaction, button, mut color, children) in buttons.iter_mut() {
+        match interaction {
+            Interaction::Pressed => match button {
+                MenuButton::Continue => {
+                    continue_game.send(ContinueGame);
+                    next_state.set(GameState::Playing);
+                }
+                MenuButton::NewGame => {
+                    new_game.send(NewGame);
+                    next_state.set(GameState::Playing);
+                }
+                MenuButton::Resume => next_state.set(GameState::Playing),
+                MenuButton::ChargeMode => {
+                    *mode = mode.next();
+                    set_label(&mut labels, children, mode.label());
+                }
+                MenuButton::Difficulty => {
+                    *difficulty = difficulty.next();
+                    set_label(&mut labels, children, difficulty.label());
+                }
+                MenuButton::ChargeMeter => {
+                    hud.charge_meter = hud.charge_meter.next();
+                    set_label(&mut labels, children, hud.charge_meter.label());
+                }
+                MenuButton::CameraMode => {
+                    *camera = camera.next();
+                    set_label(&mut labels, children, camera.label());
+                }
+                MenuButton::PlayerParticles => {
+                    *particles = particles.next();
+                    set_label(&mut labels, children, particles.label());
+                }
+                MenuButton::Title => next_state.set(GameState::Title),
+                MenuButton::Quit => {
+                    exit.send(AppExit::Success);
+                }
+            },
+            Interaction::Hovered => *color = BackgroundColor(BUTTON_HOVERED_COLOR),
+            Interaction::None => *color = BackgroundColor(BUTTON_COLOR),
+        }
+    }
+}
+
+fn set_label(labels: &mut Query<&mut Text>, children: &Children, label: &str) {
+    let mut texts = labels.iter_many_mut(children);
+    while let Some(mut text) = texts.fetch_next() {
+        text.sections[0].value = label.to_owned();
+    }
+}
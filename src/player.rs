@@ -1,11 +1,15 @@
 use self::movement::LastDirection;
+use crate::state::GameState;
 use bevy::prelude::*;
-use bevy_ecs_ldtk::{app::LdtkEntityAppExt, LdtkEntity, LdtkSpriteSheetBundle};
-use input::PlayerActionSidescroller;
+use bevy_ecs_ldtk::{app::LdtkEntityAppExt, LdtkEntity, LdtkSpriteSheetBundle, Worldly};
+use fall::FallTracker;
+use input::{PlayerActionSidescroller, TickInput};
 use leafwing_input_manager::prelude::*;
-use movement::{CharacterControllerBundle, Grounded};
+use movement::{CharacterControllerBundle, Grounded, Stunned};
 
+pub mod fall;
 pub mod input;
+pub mod jump_profile;
 pub mod movement;
 
 pub struct PlayerPlugin;
@@ -16,14 +20,13 @@ impl Plugin for PlayerPlugin {
             .add_plugins((
                 InputManagerPlugin::<input::PlayerActionSidescroller>::default(),
                 movement::CharacterControllerPlugin,
+                jump_profile::JumpProfilePlugin,
+                fall::FallPlugin,
             ))
-            .add_systems(PostUpdate, (set_player_direction, animate_sprite));
-        // .add_systems(
-        //     PostUpdate,
-        //     follow_player
-        //         .after(PhysicsSet::Sync)
-        //         .before(TransformSystem::TransformPropagate),
-        // );
+            .add_systems(
+                PostUpdate,
+                (set_player_direction, animate_sprite).run_if(in_state(GameState::Playing)),
+            );
     }
 }
 
@@ -31,11 +34,15 @@ impl Plugin for PlayerPlugin {
 struct PlayerBundle {
     #[sprite_sheet_bundle]
     sprite_sheet_bundle: LdtkSpriteSheetBundle,
+    #[worldly]
+    worldly: Worldly,
     player: Player,
     state: PlayerState,
     movement: CharacterControllerBundle,
     input: InputManagerBundle<PlayerActionSidescroller>,
+    tick_input: TickInput,
     juice: JuiceMeter,
+    fall: FallTracker,
     animation_timer: AnimationTimer,
     animation_indices: AnimationIndices,
 }
@@ -46,6 +53,8 @@ pub enum PlayerState {
     Idle,
     Walk,
     Jump,
+    /// Stunned after landing from a long fall.
+    Splat,
 }
 
 #[derive(Component)]
@@ -64,6 +73,11 @@ impl AnimationIndices {
         first: 40,
         last: 47,
     };
+    /// Flattened on the ground, wobbling until the stun wears off.
+    const SPLAT: Self = Self {
+        first: 60,
+        last: 63,
+    };
 }
 
 #[derive(Component, Deref, DerefMut)]
@@ -75,9 +89,12 @@ impl Default for PlayerBundle {
             player: Player,
             state: PlayerState::Idle,
             sprite_sheet_bundle: Default::default(),
+            worldly: Default::default(),
             input: InputManagerBundle::with_map(PlayerActionSidescroller::default_input_map()),
+            tick_input: Default::default(),
             movement: CharacterControllerBundle::default(),
             juice: Default::default(),
+            fall: Default::default(),
             animation_timer: AnimationTimer(Timer::from_seconds(0.1, TimerMode::Repeating)),
             animation_indices: AnimationIndices { first: 0, last: 19 },
         }
@@ -95,19 +112,6 @@ pub enum JuiceMeter {
     Exhausted,
 }
 
-fn follow_player(
-    mut cameras: Query<&mut Transform, With<Camera2d>>,
-    player: Query<&Transform, (With<Player>, Without<Camera2d>)>,
-) {
-    let Ok(player) = player.get_single() else {
-        return;
-    };
-
-    for mut transform in cameras.iter_mut() {
-        transform.translation = player.translation;
-    }
-}
-
 fn set_player_direction(mut player: Query<(&mut Transform, &LastDirection), With<Player>>) {
     let Ok((mut transform, last_direction)) = player.get_single_mut() else {
         return;
@@ -124,23 +128,24 @@ fn animate_sprite(
         &mut TextureAtlas,
         &mut PlayerState,
         Option<&Grounded>,
-        Option<&ActionState<PlayerActionSidescroller>>,
+        Option<&TickInput>,
+        Has<Stunned>,
     )>,
 ) {
-    for (mut indices, mut timer, mut atlas, mut state, grounded, action) in &mut query {
+    for (mut indices, mut timer, mut atlas, mut state, grounded, input, is_stunned) in &mut query {
         timer.tick(time.delta());
         if timer.just_finished() {
-            if grounded.is_some() {
-                if let Some(action) = action {
-                    let val = action
-                        .axis_data(&PlayerActionSidescroller::Move)
-                        .unwrap()
-                        .value;
+            if is_stunned {
+                if *state != PlayerState::Splat {
+                    *state = PlayerState::Splat;
+                    atlas.index = AnimationIndices::SPLAT.first;
+                }
+            } else if grounded.is_some() {
+                if let Some(input) = input {
+                    let val = input.movement;
                     const DELTA: f32 = 0.1;
 
-                    if (val >= DELTA || val <= -DELTA)
-                        && !action.pressed(&PlayerActionSidescroller::Jump)
-                    {
+                    if (val >= DELTA || val <= -DELTA) && !input.jump {
                         if *state != PlayerState::Walk {
                             *state = PlayerState::Walk;
                             atlas.index = AnimationIndices::WALK.first;
@@ -163,6 +168,7 @@ fn animate_sprite(
                 PlayerState::Idle => *indices = AnimationIndices::IDLE,
                 PlayerState::Walk => *indices = AnimationIndices::WALK,
                 PlayerState::Jump => *indices = AnimationIndices::JUMP,
+                PlayerState::Splat => *indices = AnimationIndices::SPLAT,
             }
 
             atlas.index = if atlas.index >= indices.last {
@@ -0,0 +1,101 @@
+use super::{
+    jump_profile::JumpTuning,
+    movement::{Grounded, Stunned},
+    Player,
+};
+use crate::{
+    map::{selected_level, transition::level_transition},
+    state::GameState,
+};
+use avian2d::prelude::PhysicsSet;
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+
+/// Measures how far the player falls and stuns them after long falls.
+pub struct FallPlugin;
+
+impl Plugin for FallPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PlayerFell>().add_systems(
+            FixedPostUpdate,
+            track_falls
+                .after(PhysicsSet::Sync)
+                .after(level_transition)
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+/// Sent when the player lands lower than the apex of their last airborne period.
+#[derive(Event, Debug, Clone)]
+pub struct PlayerFell {
+    /// Height lost since the apex, in LDtk pixels.
+    pub distance: f32,
+    pub from_level: LevelIid,
+    pub to_level: LevelIid,
+}
+
+/// The highest point of the current airborne period, if airborne.
+#[derive(Component, Debug, Default)]
+pub struct FallTracker {
+    apex: Option<(f32, LevelIid)>,
+}
+
+/// Falls shorter than this aren't reported, so walking down ramps stays quiet.
+const MIN_FALL_DISTANCE: f32 = 64.;
+
+fn track_falls(
+    mut commands: Commands,
+    mut player: Query<(Entity, &Transform, &mut FallTracker, Has<Grounded>), With<Player>>,
+    mut fell: EventWriter<PlayerFell>,
+    tuning: JumpTuning,
+    level_selection: Res<LevelSelection>,
+    projects: Query<&Handle<LdtkProject>>,
+    project_assets: Res<Assets<LdtkProject>>,
+) {
+    let Ok((entity, transform, mut tracker, is_grounded)) = player.get_single_mut() else {
+        return;
+    };
+
+    let Some(level) = projects
+        .get_single()
+        .ok()
+        .and_then(|handle| project_assets.get(handle))
+        .and_then(|project| selected_level(project, &level_selection))
+    else {
+        return;
+    };
+
+    // Height in LDtk world space with y pointing up, so it carries across levels.
+    let height = transform.translation.y - (level.world_y + level.px_hei) as f32;
+
+    if !is_grounded {
+        if tracker.apex.as_ref().is_none_or(|(apex, _)| height > *apex) {
+            tracker.apex = Some((height, LevelIid::new(level.iid.clone())));
+        }
+        return;
+    }
+
+    let Some((apex, from_level)) = tracker.apex.take() else {
+        return;
+    };
+
+    let distance = apex - height;
+    if distance < MIN_FALL_DISTANCE {
+        return;
+    }
+
+    let profile = tuning.profile();
+    if distance >= profile.splat_distance {
+        commands.entity(entity).insert(Stunned(Timer::from_seconds(
+            profile.splat_seconds,
+            TimerMode::Once,
+        )));
+    }
+
+    fell.send(PlayerFell {
+        distance,
+        from_level,
+        to_level: LevelIid::new(level.iid.clone()),
+    });
+}
@@ -57,3 +57,40 @@ impl PlayerActionSidescroller {
         input_map
     }
 }
+
+/// The player's input for one fixed tick. Character controllers read this rather than
+/// the [`ActionState`] directly, so that the same simulation can be driven by a replay.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq)]
+pub struct TickInput {
+    pub movement: f32,
+    pub jump: bool,
+    previous_jump: bool,
+}
+
+impl TickInput {
+    /// Moves on to the next tick with the given input.
+    pub fn advance(&mut self, movement: f32, jump: bool) {
+        self.previous_jump = self.jump;
+        self.movement = movement;
+        self.jump = jump;
+    }
+
+    pub fn jump_just_pressed(&self) -> bool {
+        self.jump && !self.previous_jump
+    }
+
+    pub fn jump_just_released(&self) -> bool {
+        !self.jump && self.previous_jump
+    }
+}
+
+/// Samples the live [`ActionState`] into [`TickInput`] once per fixed tick.
+pub fn sample_input(mut players: Query<(&ActionState<PlayerActionSidescroller>, &mut TickInput)>) {
+    for (action, mut input) in players.iter_mut() {
+        let movement = action
+            .axis_data(&PlayerActionSidescroller::Move)
+            .map_or(0., |axis| axis.value);
+
+        input.advance(movement, action.pressed(&PlayerActionSidescroller::Jump));
+    }
+}
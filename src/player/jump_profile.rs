@@ -0,0 +1,299 @@
+use super::movement::{CharacterController, JumpImpulse, MovementSpeed};
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    ecs::system::SystemParam,
+    prelude::*,
+};
+use ron::error::SpannedError;
+use serde::{Deserialize, Serialize};
+use std::{fmt, io};
+
+/// Loads the [`JumpProfile`] from `assets/player.jump.ron`. The file is watched, so
+/// edits are applied while the game runs.
+pub struct JumpProfilePlugin;
+
+impl Plugin for JumpProfilePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<JumpProfile>()
+            .init_asset_loader::<JumpProfileLoader>()
+            .init_resource::<ActiveJumpProfile>()
+            .init_resource::<ChargeMode>()
+            .add_systems(Update, apply_jump_profile);
+    }
+}
+
+/// How character controllers walk and how a charge turns into a jump.
+#[derive(Asset, TypePath, Deserialize, Debug, Clone)]
+pub struct JumpProfile {
+    /// Walking speed.
+    pub speed: f32,
+    /// Vertical velocity of a jump at a power of one.
+    pub impulse: f32,
+    /// Releasing the jump before this charge doesn't jump.
+    pub min_charge: f32,
+    /// Charging for this long exhausts the meter and jumps on its own.
+    pub max_charge: f32,
+    /// Added to the shaped charge to get the jump power.
+    pub base_power: f32,
+    /// Horizontal velocity relative to the vertical one.
+    pub horizontal_factor: f32,
+    /// Lifts the character off the ground on launch so it isn't grounded next tick.
+    pub launch_nudge: f32,
+    /// Share of the horizontal velocity kept when bouncing off a wall mid-air.
+    #[serde(default = "default_wall_bounce")]
+    pub wall_bounce: f32,
+    #[serde(default)]
+    pub curve: ChargeCurve,
+    /// Number of jump heights in [`ChargeMode::Tiers`].
+    #[serde(default = "default_tiers")]
+    pub tiers: u32,
+    /// Landing after falling at least this far, in LDtk pixels, splats the player.
+    #[serde(default = "default_splat_distance")]
+    pub splat_distance: f32,
+    /// How long a splat locks movement and charging.
+    #[serde(default = "default_splat_seconds")]
+    pub splat_seconds: f32,
+}
+
+fn default_wall_bounce() -> f32 {
+    JumpProfile::DEFAULT.wall_bounce
+}
+
+fn default_tiers() -> u32 {
+    JumpProfile::DEFAULT.tiers
+}
+
+fn default_splat_distance() -> f32 {
+    JumpProfile::DEFAULT.splat_distance
+}
+
+fn default_splat_seconds() -> f32 {
+    JumpProfile::DEFAULT.splat_seconds
+}
+
+impl JumpProfile {
+    /// Used until the asset has loaded.
+    pub const DEFAULT: Self = Self {
+        speed: 409.6,
+        impulse: 1331.2,
+        min_charge: 0.15,
+        max_charge: 1.0,
+        base_power: 0.1,
+        horizontal_factor: 0.5,
+        launch_nudge: 20.,
+        wall_bounce: 0.5,
+        curve: ChargeCurve::Linear,
+        tiers: 5,
+        splat_distance: 2048.,
+        splat_seconds: 1.,
+    };
+
+    /// The power of a jump launched with `charge`.
+    pub fn power(&self, charge: f32, mode: ChargeMode) -> f32 {
+        let curve = match mode {
+            ChargeMode::Continuous => self.curve,
+            ChargeMode::Tiers => ChargeCurve::Stepped(self.tiers),
+        };
+
+        self.base_power + curve.shape(charge, self.min_charge, self.max_charge)
+    }
+
+    /// The tier `charge` has reached in [`ChargeMode::Tiers`], counting from zero at the
+    /// minimum charge.
+    pub fn tier(&self, charge: f32) -> Option<u32> {
+        (charge >= self.min_charge).then(|| {
+            tier_of(
+                progress(charge, self.min_charge, self.max_charge),
+                self.tiers,
+            )
+        })
+    }
+
+    /// The charge at which `tier` is reached.
+    pub fn tier_charge(&self, tier: u32) -> f32 {
+        self.min_charge + (self.max_charge - self.min_charge) * tier as f32 / self.tiers as f32
+    }
+}
+
+impl Default for JumpProfile {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Maps the time spent charging onto the charge used for the jump. All curves agree
+/// at the minimum and maximum charge.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub enum ChargeCurve {
+    #[default]
+    Linear,
+    /// Raises the progress between the minimum and maximum charge to this power.
+    /// Above one, short hops get finer control.
+    Eased(f32),
+    /// Snaps the charge down to this many evenly spaced tiers.
+    Stepped(u32),
+}
+
+impl ChargeCurve {
+    pub fn shape(self, charge: f32, min: f32, max: f32) -> f32 {
+        let progress = progress(charge, min, max);
+        let progress = match self {
+            Self::Linear => progress,
+            Self::Eased(exponent) => progress.powf(exponent),
+            Self::Stepped(tiers) if tiers < 2 => 1.,
+            Self::Stepped(tiers) => tier_of(progress, tiers) as f32 / (tiers - 1) as f32,
+        };
+
+        min + (max - min) * progress
+    }
+}
+
+/// How far `charge` is from `min` to `max`, from zero to one.
+fn progress(charge: f32, min: f32, max: f32) -> f32 {
+    if max <= min {
+        return 1.;
+    }
+
+    ((charge - min) / (max - min)).clamp(0., 1.)
+}
+
+fn tier_of(progress: f32, tiers: u32) -> u32 {
+    ((progress * tiers as f32) as u32).min(tiers.saturating_sub(1))
+}
+
+/// Whether charging is continuous or snaps to the profile's tiers so that every jump
+/// height can be reproduced exactly. Chosen per save.
+#[derive(Resource, Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ChargeMode {
+    #[default]
+    Continuous,
+    Tiers,
+}
+
+impl ChargeMode {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Continuous => Self::Tiers,
+            Self::Tiers => Self::Continuous,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Continuous => "Jumps: Smooth",
+            Self::Tiers => "Jumps: Tiers",
+        }
+    }
+}
+
+/// The profile used by every character controller.
+#[derive(Resource)]
+pub struct ActiveJumpProfile(pub Handle<JumpProfile>);
+
+impl FromWorld for ActiveJumpProfile {
+    fn from_world(world: &mut World) -> Self {
+        Self(world.resource::<AssetServer>().load("player.jump.ron"))
+    }
+}
+
+/// Read access to the active [`JumpProfile`], falling back to
+/// [`JumpProfile::DEFAULT`] while it loads or if it failed to.
+#[derive(SystemParam)]
+pub struct JumpTuning<'w> {
+    active: Res<'w, ActiveJumpProfile>,
+    profiles: Res<'w, Assets<JumpProfile>>,
+    mode: Res<'w, ChargeMode>,
+}
+
+impl JumpTuning<'_> {
+    pub fn profile(&self) -> &JumpProfile {
+        self.profiles
+            .get(&self.active.0)
+            .unwrap_or(&JumpProfile::DEFAULT)
+    }
+
+    pub fn mode(&self) -> ChargeMode {
+        *self.mode
+    }
+
+    pub fn power(&self, charge: f32) -> f32 {
+        self.profile().power(charge, *self.mode)
+    }
+
+    /// The reached tier, or `None` when not charging in tiers.
+    pub fn tier(&self, charge: f32) -> Option<u32> {
+        match *self.mode {
+            ChargeMode::Continuous => None,
+            ChargeMode::Tiers => self.profile().tier(charge),
+        }
+    }
+}
+
+#[derive(Default)]
+struct JumpProfileLoader;
+
+#[derive(Debug)]
+pub enum JumpProfileError {
+    Io(io::Error),
+    Ron(SpannedError),
+}
+
+impl fmt::Display for JumpProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "could not read jump profile: {err}"),
+            Self::Ron(err) => write!(f, "invalid jump profile: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for JumpProfileError {}
+
+impl AssetLoader for JumpProfileLoader {
+    type Asset = JumpProfile;
+    type Settings = ();
+    type Error = JumpProfileError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<JumpProfile, JumpProfileError> {
+        let mut bytes = Vec::new();
+        reader
+            .read_to_end(&mut bytes)
+            .await
+            .map_err(JumpProfileError::Io)?;
+
+        ron::de::from_bytes(&bytes).map_err(JumpProfileError::Ron)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["jump.ron"]
+    }
+}
+
+/// Copies the walking speed and jump impulse onto character controllers when they
+/// spawn and whenever the profile is reloaded.
+fn apply_jump_profile(
+    mut events: EventReader<AssetEvent<JumpProfile>>,
+    tuning: JumpTuning,
+    mut controllers: Query<(&mut MovementSpeed, &mut JumpImpulse), With<CharacterController>>,
+    added: Query<(), Added<CharacterController>>,
+) {
+    let id = tuning.active.0.id();
+    let reloaded = events
+        .read()
+        .any(|event| event.is_loaded_with_dependencies(id) || event.is_modified(id));
+
+    if !reloaded && added.is_empty() {
+        return;
+    }
+
+    let profile = tuning.profile();
+    for (mut speed, mut impulse) in &mut controllers {
+        speed.0 = profile.speed;
+        impulse.0 = profile.impulse;
+    }
+}
@@ -1,23 +1,79 @@
-use super::{input::PlayerActionSidescroller, JuiceMeter, Player};
-use crate::GRAVITY;
+use super::{
+    input::{sample_input, TickInput},
+    jump_profile::{JumpProfile, JumpTuning},
+    JuiceMeter,
+};
+use crate::{
+    map::collision::{level_spawned, Surface, SurfaceMaterial},
+    state::GameState,
+};
 use avian2d::{math::*, prelude::*};
 use bevy::{ecs::query::Has, prelude::*};
-use leafwing_input_manager::action_state::ActionState;
 
 pub struct CharacterControllerPlugin;
 
 impl Plugin for CharacterControllerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
-            FixedPreUpdate,
-            (update_grounded, movement, handle_jump, handle_elasticity)
-                .chain()
-                .before(PhysicsSet::Prepare)
-                .before(PhysicsSet::StepSimulation),
-        );
+        app.add_event::<Jumped>()
+            .add_event::<ChargeTierReached>()
+            .add_event::<WallBounce>()
+            .add_systems(
+                FixedPreUpdate,
+                (
+                    sample_input.in_set(ControllerInputSet),
+                    (
+                        update_grounded,
+                        recover_from_stun,
+                        movement,
+                        handle_jump,
+                        store_pre_step_velocity,
+                    )
+                        .chain()
+                        .after(ControllerInputSet),
+                )
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(level_spawned)
+                    .before(PhysicsSet::Prepare)
+                    .before(PhysicsSet::StepSimulation),
+            )
+            .add_systems(
+                FixedPostUpdate,
+                bounce_off_walls
+                    .after(PhysicsSet::StepSimulation)
+                    .before(PhysicsSet::Sync)
+                    .run_if(in_state(GameState::Playing)),
+            );
     }
 }
 
+/// Fills each controller's [`TickInput`] before the controller systems read it.
+/// Systems added to this set after [`sample_input`] can replace the live input.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ControllerInputSet;
+
+/// Sent when a character controller launches a jump.
+#[derive(Event, Debug)]
+pub struct Jumped {
+    pub entity: Entity,
+    pub charge: Scalar,
+}
+
+/// Sent when a charge in [`ChargeMode::Tiers`](super::jump_profile::ChargeMode::Tiers)
+/// reaches the next jump height.
+#[derive(Event, Debug)]
+pub struct ChargeTierReached {
+    pub entity: Entity,
+    pub tier: u32,
+}
+
+/// Sent when an airborne character controller bounces off a side wall.
+#[derive(Event, Debug)]
+pub struct WallBounce {
+    pub entity: Entity,
+    /// The wall's normal, pointing towards the character.
+    pub normal: Vector,
+}
+
 /// A marker component indicating that an entity is using a character controller.
 #[derive(Component)]
 pub struct CharacterController;
@@ -27,13 +83,38 @@ pub struct CharacterController;
 #[component(storage = "SparseSet")]
 pub struct Grounded;
 
+/// A marker component indicating that an entity stands on a slope steeper than its
+/// [`MaxSlopeAngle`] and is sliding down it.
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub struct Sliding;
+
+/// The [`Surface`] under a grounded character.
+#[derive(Component, Debug, Clone, Copy)]
+#[component(storage = "SparseSet")]
+pub struct GroundSurface(pub Surface);
+
+/// The velocity of whatever a grounded character stands on, like a moving platform.
+#[derive(Component, Debug, Clone, Copy)]
+#[component(storage = "SparseSet")]
+pub struct GroundVelocity(pub Vector);
+
+/// Locks movement and jump charging, e.g. after a long fall.
+#[derive(Component, Deref, DerefMut)]
+pub struct Stunned(pub Timer);
+
+/// The velocity a character controller entered the physics step with, before the
+/// solver stopped it against whatever it hit.
+#[derive(Component, Default)]
+pub struct PreStepVelocity(pub Vector);
+
 /// The acceleration used for character movement.
 #[derive(Component)]
-pub struct MovementSpeed(Scalar);
+pub struct MovementSpeed(pub Scalar);
 
 /// The strength of a jump.
 #[derive(Component)]
-pub struct JumpImpulse(Scalar);
+pub struct JumpImpulse(pub Scalar);
 
 /// The last direction the player faced when moving.
 #[derive(Component)]
@@ -55,6 +136,9 @@ pub struct CharacterControllerBundle {
     ground_caster: ShapeCaster,
     locked_axes: LockedAxes,
     movement: MovementBundle,
+    pre_step_velocity: PreStepVelocity,
+    gravity_scale: GravityScale,
+    damping: LinearDamping,
     restitution: Restitution,
     friction: Friction,
     margin: CollisionMargin,
@@ -86,9 +170,17 @@ impl MovementBundle {
     }
 }
 
+/// Steepest slope the player can stand and charge a jump on. Like in Jump King, only
+/// gentle 22.5° ramps can be walked on and 45° ramps always slide.
+const PLAYER_MAX_SLOPE_ANGLE: Scalar = PI * 0.15;
+
 impl Default for MovementBundle {
     fn default() -> Self {
-        Self::new(GRAVITY * 0.2, GRAVITY * 0.65, PI * 0.45)
+        Self::new(
+            JumpProfile::DEFAULT.speed,
+            JumpProfile::DEFAULT.impulse,
+            PLAYER_MAX_SLOPE_ANGLE,
+        )
     }
 }
 
@@ -106,6 +198,9 @@ impl CharacterControllerBundle {
                 .with_max_time_of_impact(10.),
             locked_axes: LockedAxes::ROTATION_LOCKED,
             movement: MovementBundle::default(),
+            pre_step_velocity: PreStepVelocity::default(),
+            gravity_scale: GravityScale(1.),
+            damping: LinearDamping(0.),
             restitution: Restitution::PERFECTLY_INELASTIC
                 .with_combine_rule(CoefficientCombine::Min),
             friction: Friction::ZERO.with_combine_rule(CoefficientCombine::Min),
@@ -125,110 +220,196 @@ impl CharacterControllerBundle {
     }
 }
 
-/// Updates the [`Grounded`] status for character controllers.
+/// Updates the [`Grounded`], [`Sliding`], [`GroundSurface`] and [`GroundVelocity`] status
+/// for character controllers.
 fn update_grounded(
     mut commands: Commands,
     mut query: Query<
         (Entity, &ShapeHits, &Rotation, Option<&MaxSlopeAngle>),
         With<CharacterController>,
     >,
+    surfaces: Query<&Surface>,
+    velocities: Query<&LinearVelocity, Without<CharacterController>>,
 ) {
     for (entity, hits, rotation, max_slope_angle) in &mut query {
+        let mut ground = None;
+        let mut on_steep_slope = false;
+
         // The character is grounded if the shape caster has a hit with a normal
-        // that isn't too steep.
-        let is_grounded = hits.iter().any(|hit| {
-            if let Some(angle) = max_slope_angle {
-                (rotation * -hit.normal2).angle_between(Vector::Y).abs() <= angle.0
-            } else {
-                true
+        // that isn't too steep. Hits that still face upwards but are steeper than
+        // that make the character slide.
+        for hit in hits.iter() {
+            let angle = (rotation * -hit.normal2).angle_between(Vector::Y).abs();
+
+            if max_slope_angle.is_none_or(|max| angle <= max.0) {
+                ground = Some(hit);
+                break;
+            } else if angle < PI / 2. {
+                on_steep_slope = true;
             }
-        });
+        }
 
-        if is_grounded {
-            commands.entity(entity).insert(Grounded);
+        if let Some(ground) = ground {
+            let surface = surfaces.get(ground.entity).copied().unwrap_or_default();
+            let velocity = velocities.get(ground.entity).map_or(Vector::ZERO, |v| v.0);
+            commands
+                .entity(entity)
+                .insert((Grounded, GroundSurface(surface), GroundVelocity(velocity)))
+                .remove::<Sliding>();
+        } else if on_steep_slope {
+            commands
+                .entity(entity)
+                .insert(Sliding)
+                .remove::<(Grounded, GroundSurface, GroundVelocity)>();
         } else {
-            commands.entity(entity).remove::<Grounded>();
+            commands
+                .entity(entity)
+                .remove::<(Grounded, GroundSurface, GroundVelocity, Sliding)>();
+        }
+    }
+}
+
+fn recover_from_stun(
+    mut commands: Commands,
+    mut stunned: Query<(Entity, &mut Stunned)>,
+    time: Res<Time>,
+) {
+    for (entity, mut stun) in &mut stunned {
+        if stun.tick(time.delta()).finished() {
+            commands.entity(entity).remove::<Stunned>();
         }
     }
 }
 
-/// Responds to [`MovementAction`] events and moves character controllers accordingly.
+fn ground_material(surface: Option<&GroundSurface>) -> SurfaceMaterial {
+    surface.map_or(SurfaceMaterial::SOLID, |surface| surface.0.material())
+}
+
+/// Responds to [`TickInput`] and moves character controllers accordingly.
 fn movement(
-    action: Query<&ActionState<PlayerActionSidescroller>, With<Player>>,
     mut controllers: Query<(
+        &TickInput,
         &MovementSpeed,
         &JuiceMeter,
         &mut LinearVelocity,
         &mut LastDirection,
-        &mut Transform,
+        Option<&GroundSurface>,
+        Option<&GroundVelocity>,
         Has<Grounded>,
+        Has<Stunned>,
     )>,
+    time: Res<Time>,
 ) {
-    let Ok(action) = action.get_single() else {
-        return;
-    };
-
-    for (speed, juice, mut linear_velocity, mut last_direction, mut transform, is_grounded) in
-        &mut controllers
+    for (
+        input,
+        speed,
+        juice,
+        mut linear_velocity,
+        mut last_direction,
+        surface,
+        ground_velocity,
+        is_grounded,
+        is_stunned,
+    ) in &mut controllers
     {
-        let value = action.axis_data(&PlayerActionSidescroller::Move).unwrap();
+        let material = ground_material(surface);
+        // Movement is relative to the ground, so characters ride moving platforms.
+        let ground_velocity = ground_velocity.map_or(Vector::ZERO, |ground| ground.0);
 
         // jump king controls
 
         if is_grounded {
-            if matches!(juice, JuiceMeter::Charging(_)) {
-                linear_velocity.x = 0.;
-            } else if value.value > 0.2 {
+            let target = if is_stunned || matches!(juice, JuiceMeter::Charging(_)) {
+                0.
+            } else if input.movement > 0.2 {
                 last_direction.0 = 1.;
-                linear_velocity.x = speed.0;
-            } else if value.value < -0.2 {
+                speed.0 * material.speed
+            } else if input.movement < -0.2 {
                 last_direction.0 = -1.;
-                linear_velocity.x = -speed.0;
+                -speed.0 * material.speed
             } else {
-                linear_velocity.x = 0.;
-            }
+                0.
+            };
+
+            // Low traction surfaces like ice keep some of the previous velocity.
+            let grip = if material.traction.is_infinite() {
+                1.
+            } else {
+                1. - (-material.traction * time.delta_seconds()).exp()
+            };
+            linear_velocity.x += (target + ground_velocity.x - linear_velocity.x) * grip;
 
-            linear_velocity.y = linear_velocity.y.max(0.);
+            // Don't build up falling speed while standing, but follow platforms down.
+            linear_velocity.y = linear_velocity.y.max(ground_velocity.y.min(0.));
         }
     }
 }
 
 fn handle_jump(
-    action: Query<&ActionState<PlayerActionSidescroller>, With<Player>>,
     mut controllers: Query<(
+        Entity,
+        &TickInput,
         &JumpImpulse,
         &mut LinearVelocity,
         &mut LastDirection,
         &mut Transform,
         &mut JuiceMeter,
+        Option<&GroundSurface>,
+        Option<&GroundVelocity>,
         Has<Grounded>,
+        Has<Sliding>,
+        Has<Stunned>,
     )>,
+    mut jumped: EventWriter<Jumped>,
+    mut tier_reached: EventWriter<ChargeTierReached>,
+    tuning: JumpTuning,
     time: Res<Time>,
 ) {
-    let Ok(action) = action.get_single() else {
-        return;
-    };
+    let profile = tuning.profile();
 
     for (
+        entity,
+        input,
         jump_impulse,
         mut linear_velocity,
         last_direction,
         mut transform,
         mut juice,
+        surface,
+        ground_velocity,
         is_grounded,
+        is_sliding,
+        is_stunned,
     ) in &mut controllers
     {
+        let impulse = jump_impulse.0 * ground_material(surface).jump;
+
+        // A charge can't survive sliding off onto a steep slope.
+        if is_sliding && matches!(*juice, JuiceMeter::Charging(_)) {
+            *juice = JuiceMeter::Idle;
+        }
+
+        if is_stunned {
+            continue;
+        }
+
         match *juice {
             JuiceMeter::Idle => {
-                if is_grounded && action.just_pressed(&PlayerActionSidescroller::Jump) {
+                if is_grounded && input.jump_just_pressed() {
                     *juice = JuiceMeter::Charging(0.);
                 }
             }
-            JuiceMeter::Charging(charge) => {
-                let charge = charge + time.delta_seconds();
+            JuiceMeter::Charging(previous) => {
+                let charge = previous + time.delta_seconds();
 
-                let released = action.just_released(&PlayerActionSidescroller::Jump);
-                let overcharged = charge >= 1.;
+                if let Some(tier) = tuning.tier(charge) {
+                    if tuning.tier(previous) != Some(tier) {
+                        tier_reached.send(ChargeTierReached { entity, tier });
+                    }
+                }
+
+                let released = input.jump_just_released();
+                let overcharged = charge >= profile.max_charge;
 
                 if released {
                     *juice = JuiceMeter::Idle;
@@ -238,17 +419,22 @@ fn handle_jump(
                     *juice = JuiceMeter::Charging(charge);
                 }
 
-                if charge >= 0.15 && (released || overcharged) {
-                    if is_grounded {
-                        linear_velocity.x =
-                            jump_impulse.0 * last_direction.0 * 0.5 * (0.1 + charge);
-                        linear_velocity.y = jump_impulse.0 * (0.1 + charge);
-                        transform.translation.y += 20.;
-                    }
+                if charge >= profile.min_charge && (released || overcharged) && is_grounded {
+                    let power = tuning.power(charge);
+                    let launch = Vector::new(
+                        impulse * last_direction.0 * profile.horizontal_factor * power,
+                        impulse * power,
+                    );
+                    // Jumps off moving platforms carry their momentum.
+                    linear_velocity.0 =
+                        launch + ground_velocity.map_or(Vector::ZERO, |ground| ground.0);
+                    transform.translation.y += profile.launch_nudge;
+
+                    jumped.send(Jumped { entity, charge });
                 }
             }
             JuiceMeter::Exhausted => {
-                if action.just_released(&PlayerActionSidescroller::Jump) {
+                if input.jump_just_released() {
                     *juice = JuiceMeter::Idle;
                 }
             }
@@ -256,12 +442,84 @@ fn handle_jump(
     }
 }
 
-fn handle_elasticity(mut controllers: Query<(&LinearVelocity, &mut Restitution, Has<Grounded>)>) {
-    for (velocity, mut rest, is_grounded) in controllers.iter_mut() {
-        if !is_grounded && velocity.y > 100. {
-            *rest = Restitution::PERFECTLY_ELASTIC;
+fn store_pre_step_velocity(mut controllers: Query<(&LinearVelocity, &mut PreStepVelocity)>) {
+    for (velocity, mut pre_step) in &mut controllers {
+        pre_step.0 = velocity.0;
+    }
+}
+
+/// Contacts whose normal is at most this far from horizontal count as side walls.
+const WALL_NORMAL_Y: Scalar = 0.3;
+/// Contacts whose normal points at least this far down count as ceilings.
+const CEILING_NORMAL_Y: Scalar = -0.7;
+
+/// Reflects the horizontal velocity of airborne characters that hit a side wall, stops
+/// them rising when they hit a ceiling and bounces them off floors with a bouncy
+/// [`Surface`]. The controller is perfectly inelastic, so the solver alone would stop
+/// them dead.
+fn bounce_off_walls(
+    mut collisions: EventReader<Collision>,
+    mut controllers: Query<
+        (
+            &Rotation,
+            &PreStepVelocity,
+            &mut LinearVelocity,
+            Has<Grounded>,
+        ),
+        With<CharacterController>,
+    >,
+    surfaces: Query<&Surface>,
+    mut bounces: EventWriter<WallBounce>,
+    tuning: JumpTuning,
+) {
+    let damping = tuning.profile().wall_bounce;
+
+    for Collision(contacts) in collisions.read() {
+        if contacts.is_sensor || contacts.during_previous_frame {
+            continue;
+        }
+
+        let (entity, other, is_first) = if controllers.contains(contacts.entity1) {
+            (contacts.entity1, contacts.entity2, true)
+        } else if controllers.contains(contacts.entity2) {
+            (contacts.entity2, contacts.entity1, false)
         } else {
-            *rest = Restitution::PERFECTLY_INELASTIC;
+            continue;
+        };
+
+        let Ok((rotation, pre_step, mut velocity, is_grounded)) = controllers.get_mut(entity)
+        else {
+            continue;
+        };
+
+        let bounce = surfaces
+            .get(other)
+            .map_or(0., |surface| surface.material().bounce);
+
+        for manifold in contacts.manifolds.iter() {
+            let normal = if is_first {
+                -manifold.global_normal1(rotation)
+            } else {
+                -manifold.global_normal2(rotation)
+            };
+
+            if normal.y > WALL_NORMAL_Y {
+                // The shape caster may already see the floor, so landings are handled
+                // whether or not the character counts as grounded.
+                if bounce > 0. && pre_step.0.y < 0. {
+                    velocity.y = -pre_step.0.y * bounce;
+                }
+            } else if is_grounded {
+                continue;
+            } else if normal.y.abs() <= WALL_NORMAL_Y {
+                // Only bounce off walls the character was moving into.
+                if pre_step.0.x * normal.x < 0. {
+                    velocity.x = -pre_step.0.x * damping;
+                    bounces.send(WallBounce { entity, normal });
+                }
+            } else if normal.y <= CEILING_NORMAL_Y {
+                velocity.y = velocity.y.min(0.);
+            }
         }
     }
 }
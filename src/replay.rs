@@ -0,0 +1,388 @@
+use crate::{
+    map::collision::level_spawned,
+    player::{
+        input::{sample_input, TickInput},
+        movement::ControllerInputSet,
+        Player,
+    },
+    save::{PendingRestore, SaveData, SaveError, SaveSource},
+    state::GameState,
+};
+use avian2d::prelude::LinearVelocity;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Bumped whenever [`Replay`] changes shape.
+pub const REPLAY_VERSION: u32 = 1;
+
+/// Records every run to the replays directory, or plays back the replay given with
+/// `--replay <path>` instead of reading live input.
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        let playback = replay_arg().and_then(|path| match Replay::read(&path) {
+            Ok(replay) => Some(Playback::new(replay)),
+            Err(err) => {
+                error!("failed to load replay {}: {err}", path.display());
+                None
+            }
+        });
+
+        if let Some(playback) = playback {
+            app.insert_resource(playback)
+                .add_systems(Startup, start_playback)
+                .add_systems(
+                    FixedPreUpdate,
+                    play_back_input
+                        .in_set(ControllerInputSet)
+                        .after(sample_input)
+                        .run_if(in_state(GameState::Playing))
+                        .run_if(not(resource_exists::<PendingRestore>))
+                        .run_if(level_spawned),
+                );
+        } else {
+            app.init_resource::<Recording>()
+                .add_systems(
+                    FixedPreUpdate,
+                    record_input
+                        .in_set(ControllerInputSet)
+                        .after(sample_input)
+                        .run_if(in_state(GameState::Playing))
+                        .run_if(not(resource_exists::<PendingRestore>))
+                        .run_if(level_spawned),
+                )
+                .add_systems(OnEnter(GameState::Victory), write_recording)
+                .add_systems(Last, write_recording_on_exit);
+        }
+    }
+}
+
+/// The input of a run, starting from a known player state.
+///
+/// Only what a save keeps is stored. Runs are recorded from the start of a new game or
+/// a continued save and played back from launch, so the levels, their platforms and
+/// wind, and everything else about the player start out freshly spawned or reset by
+/// restoring the save either way.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Replay {
+    pub version: u32,
+    pub start: SaveData,
+    pub start_velocity: [f32; 2],
+    /// Run-length encoded input, one run per stretch of identical ticks.
+    pub inputs: Vec<InputRun>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct InputRun {
+    pub ticks: u32,
+    pub movement: f32,
+    pub jump: bool,
+}
+
+impl Replay {
+    pub fn dir() -> Result<PathBuf, SaveError> {
+        SaveData::path().map(|path| path.with_file_name("replays"))
+    }
+
+    pub fn read(path: &Path) -> Result<Self, SaveError> {
+        let contents = fs::read_to_string(path)?;
+        let replay: Self = ron::from_str(&contents).map_err(SaveError::Corrupt)?;
+
+        if replay.version != REPLAY_VERSION {
+            return Err(SaveError::Version {
+                found: replay.version,
+                expected: REPLAY_VERSION,
+            });
+        }
+
+        Ok(replay)
+    }
+
+    pub fn write(&self) -> Result<PathBuf, SaveError> {
+        let dir = Self::dir()?;
+        fs::create_dir_all(&dir)?;
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        let path = dir.join(format!("replay-{timestamp}.ron"));
+
+        fs::write(&path, ron::to_string(self).map_err(SaveError::Serialize)?)?;
+
+        Ok(path)
+    }
+
+    fn push(&mut self, movement: f32, jump: bool) {
+        match self.inputs.last_mut() {
+            Some(run) if run.movement == movement && run.jump == jump => run.ticks += 1,
+            _ => self.inputs.push(InputRun {
+                ticks: 1,
+                movement,
+                jump,
+            }),
+        }
+    }
+}
+
+/// Run condition for systems that must not touch the player's files during playback,
+/// like the autosave.
+pub fn playing_back(playback: Option<Res<Playback>>) -> bool {
+    playback.is_some()
+}
+
+fn replay_arg() -> Option<PathBuf> {
+    let mut args = std::env::args().skip_while(|arg| *arg != "--replay");
+    args.next()?;
+    args.next().map(PathBuf::from)
+}
+
+/// The run being recorded. Starts with the first tick played, which is always the first
+/// one after the title menu.
+#[derive(Resource, Default)]
+struct Recording(Option<Replay>);
+
+#[derive(Resource)]
+pub struct Playback {
+    replay: Replay,
+    run: usize,
+    tick: u32,
+    started: bool,
+}
+
+impl Playback {
+    fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            run: 0,
+            tick: 0,
+            started: false,
+        }
+    }
+}
+
+fn record_input(
+    mut recording: ResMut<Recording>,
+    source: SaveSource,
+    players: Query<(&TickInput, &LinearVelocity), With<Player>>,
+) {
+    let Ok((input, velocity)) = players.get_single() else {
+        return;
+    };
+
+    if recording.0.is_none() {
+        let Some(start) = source.snapshot() else {
+            return;
+        };
+
+        recording.0 = Some(Replay {
+            version: REPLAY_VERSION,
+            start,
+            start_velocity: velocity.0.to_array(),
+            inputs: Vec::new(),
+        });
+    }
+
+    if let Some(replay) = recording.0.as_mut() {
+        replay.push(input.movement, input.jump);
+    }
+}
+
+fn write_recording(mut recording: ResMut<Recording>) {
+    let Some(replay) = recording.0.take() else {
+        return;
+    };
+
+    match replay.write() {
+        Ok(path) => info!("wrote replay to {}", path.display()),
+        Err(err) => error!("failed to write replay: {err}"),
+    }
+}
+
+fn write_recording_on_exit(exit: EventReader<AppExit>, recording: ResMut<Recording>) {
+    if !exit.is_empty() {
+        write_recording(recording);
+    }
+}
+
+/// Skips the title menu and restores the replay's starting state.
+fn start_playback(
+    mut commands: Commands,
+    playback: Res<Playback>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    commands.insert_resource(PendingRestore(playback.replay.start.clone()));
+    next_state.set(GameState::Playing);
+}
+
+/// Overwrites the live input with the recorded one, tick by tick.
+fn play_back_input(
+    mut playback: ResMut<Playback>,
+    mut players: Query<(&mut TickInput, &mut LinearVelocity), With<Player>>,
+) {
+    let Ok((mut input, mut velocity)) = players.get_single_mut() else {
+        return;
+    };
+
+    if !playback.started {
+        velocity.0 = Vec2::from_array(playback.replay.start_velocity);
+        playback.started = true;
+    }
+
+    // `sample_input` has already moved the previous tick's recorded jump into place, so
+    // only the current values are replaced.
+    let run = playback.replay.inputs.get(playback.run).copied();
+    input.movement = run.map_or(0., |run| run.movement);
+    input.jump = run.is_some_and(|run| run.jump);
+
+    let Some(run) = run else {
+        return;
+    };
+
+    playback.tick += 1;
+    if playback.tick >= run.ticks {
+        playback.tick = 0;
+        playback.run += 1;
+
+        if playback.run == playback.replay.inputs.len() {
+            info!("replay finished");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        map::{collision::SpawnedLevels, hazard::RespawnPoint},
+        player::{
+            input::PlayerActionSidescroller,
+            jump_profile::{ActiveJumpProfile, ChargeMode, JumpProfile},
+            movement::{CharacterControllerBundle, CharacterControllerPlugin},
+            JuiceMeter,
+        },
+        run::{Difficulty, RunStats},
+    };
+    use avian2d::prelude::*;
+    use bevy::{
+        hierarchy::HierarchyPlugin, state::app::StatesPlugin, time::TimeUpdateStrategy,
+        utils::HashSet,
+    };
+    use bevy_ecs_ldtk::prelude::*;
+    use leafwing_input_manager::prelude::ActionState;
+
+    const LEVEL_IID: &str = "replay-test-level";
+    const TICKS: u32 = 240;
+
+    /// A floor and the player standing above it, without a window or an LDtk project.
+    fn app(start: Vec3) -> App {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            HierarchyPlugin,
+            TransformPlugin,
+            StatesPlugin,
+            bevy::scene::ScenePlugin,
+            PhysicsPlugins::new(FixedPostUpdate),
+            CharacterControllerPlugin,
+        ))
+        .init_resource::<Assets<Mesh>>()
+        .init_asset::<LdtkProject>()
+        .init_asset::<JumpProfile>()
+        .insert_resource(ActiveJumpProfile(Handle::default()))
+        .insert_state(GameState::Playing)
+        .insert_resource(Gravity(Vec2::NEG_Y * crate::GRAVITY))
+        .insert_resource(LevelSelection::iid(LEVEL_IID))
+        .insert_resource(SpawnedLevels(HashSet::from([LevelIid::new(LEVEL_IID)])))
+        .init_resource::<ChargeMode>()
+        .init_resource::<RunStats>()
+        .init_resource::<Difficulty>()
+        .init_resource::<RespawnPoint>()
+        .insert_resource(TimeUpdateStrategy::ManualDuration(
+            Time::<Fixed>::default().timestep(),
+        ));
+
+        app.world_mut().spawn((
+            RigidBody::Static,
+            Collider::rectangle(8192., 256.),
+            TransformBundle::from_transform(Transform::from_xyz(0., -128., 0.)),
+        ));
+        app.world_mut().spawn((
+            Player,
+            CharacterControllerBundle::default(),
+            TickInput::default(),
+            ActionState::<PlayerActionSidescroller>::default(),
+            JuiceMeter::default(),
+            // The ground caster is sized for the collider at half scale.
+            TransformBundle::from_transform(
+                Transform::from_translation(start).with_scale(Vec3::new(0.5, 0.5, 1.)),
+            ),
+        ));
+
+        app
+    }
+
+    /// Walks right, charges a jump and releases it while turning left, then lands.
+    fn scripted_input(mut players: Query<&mut TickInput, With<Player>>, mut tick: Local<u32>) {
+        *tick += 1;
+        for mut input in &mut players {
+            (input.movement, input.jump) = match *tick {
+                0..=40 => (1., false),
+                41..=80 => (0., true),
+                81..=120 => (-1., false),
+                _ => (0., false),
+            };
+        }
+    }
+
+    fn run(app: &mut App) -> Vec3 {
+        for _ in 0..TICKS {
+            app.update();
+        }
+
+        app.world_mut()
+            .query_filtered::<&Transform, With<Player>>()
+            .single(app.world())
+            .translation
+    }
+
+    #[test]
+    fn playback_ends_where_the_recording_did() {
+        let start = Vec3::new(0., 200., 0.);
+
+        let mut recorder = app(start);
+        recorder.init_resource::<Recording>().add_systems(
+            FixedPreUpdate,
+            (scripted_input, record_input)
+                .chain()
+                .in_set(ControllerInputSet)
+                .after(sample_input),
+        );
+        let recorded = run(&mut recorder);
+        let replay = recorder
+            .world_mut()
+            .resource_mut::<Recording>()
+            .0
+            .take()
+            .expect("the run was recorded");
+
+        assert!(recorded.distance(start) > 100., "the player moved");
+
+        let mut player = app(Vec3::from_array(replay.start.translation));
+        player.insert_resource(Playback::new(replay)).add_systems(
+            FixedPreUpdate,
+            play_back_input
+                .in_set(ControllerInputSet)
+                .after(sample_input),
+        );
+        let played_back = run(&mut player);
+
+        assert_eq!(recorded, played_back);
+    }
+}
@@ -0,0 +1,155 @@
+use crate::{
+    map::{collision::level_spawned, transition::LevelTransition, GoalReached},
+    menu::{spawn_button, MenuButton},
+    player::movement::Jumped,
+    state::GameState,
+};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+pub struct RunPlugin;
+
+impl Plugin for RunPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RunStats>()
+            .init_resource::<Difficulty>()
+            .add_systems(
+                FixedUpdate,
+                tick_run_time.run_if(in_state(GameState::Playing).and_then(level_spawned)),
+            )
+            .add_systems(
+                Update,
+                (count_jumps, count_falls, finish_run).run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(OnEnter(GameState::Victory), spawn_victory_screen);
+    }
+}
+
+/// Statistics of the current climb.
+#[derive(Resource, Debug, Default, Clone)]
+pub struct RunStats {
+    pub time: Duration,
+    /// Fixed ticks the climb has run for, the clock ghosts are sampled on.
+    pub ticks: u32,
+    pub jumps: u32,
+    /// Number of times the player fell back into a lower level.
+    pub falls: u32,
+}
+
+/// How forgiving the climb is. Chosen per save.
+#[derive(Resource, Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Difficulty {
+    /// No checkpoints, hazards send the player back to where the climb started.
+    #[default]
+    Classic,
+    /// Touching a checkpoint makes it the respawn point.
+    Checkpoints,
+}
+
+impl Difficulty {
+    pub fn has_checkpoints(self) -> bool {
+        self == Self::Checkpoints
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Self::Classic => Self::Checkpoints,
+            Self::Checkpoints => Self::Classic,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Classic => "Mode: Classic",
+            Self::Checkpoints => "Mode: Checkpoints",
+        }
+    }
+}
+
+/// Counts fixed ticks while the level is ready, like the gameplay the time measures.
+fn tick_run_time(time: Res<Time>, mut stats: ResMut<RunStats>) {
+    stats.time += time.delta();
+    stats.ticks += 1;
+}
+
+fn count_jumps(mut jumps: EventReader<Jumped>, mut stats: ResMut<RunStats>) {
+    stats.jumps += jumps.read().count() as u32;
+}
+
+/// Counts every drop into a lower level, however short. [`PlayerFell`] only reports
+/// falls past a minimum distance, so it would miss short drops over a level edge.
+///
+/// [`PlayerFell`]: crate::player::fall::PlayerFell
+fn count_falls(mut transitions: EventReader<LevelTransition>, mut stats: ResMut<RunStats>) {
+    stats.falls += transitions
+        .read()
+        .filter(|transition| !transition.upwards)
+        .count() as u32;
+}
+
+fn finish_run(mut goals: EventReader<GoalReached>, mut next_state: ResMut<NextState<GameState>>) {
+    if goals.read().count() > 0 {
+        next_state.set(GameState::Victory);
+    }
+}
+
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        duration.subsec_millis()
+    )
+}
+
+fn spawn_victory_screen(mut commands: Commands, stats: Res<RunStats>) {
+    let text_style = TextStyle {
+        font_size: 48.,
+        color: Color::WHITE,
+        ..Default::default()
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(16.),
+                    ..Default::default()
+                },
+                background_color: BackgroundColor(Color::srgba(0., 0., 0., 0.6)),
+                ..Default::default()
+            },
+            StateScoped(GameState::Victory),
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "You made it!",
+                TextStyle {
+                    font_size: 72.,
+                    ..text_style.clone()
+                },
+            ));
+            parent.spawn(TextBundle::from_section(
+                format!("Time: {}", format_duration(stats.time)),
+                text_style.clone(),
+            ));
+            parent.spawn(TextBundle::from_section(
+                format!("Jumps: {}", stats.jumps),
+                text_style.clone(),
+            ));
+            parent.spawn(TextBundle::from_section(
+                format!("Falls: {}", stats.falls),
+                text_style,
+            ));
+            spawn_button(parent, MenuButton::Title, "Title");
+            spawn_button(parent, MenuButton::Quit, "Quit");
+        });
+}
@@ -0,0 +1,373 @@
+use crate::{
+    map::{
+        hazard::{Respawn, RespawnPoint},
+        selected_level_iid,
+    },
+    menu::{ContinueGame, NewGame},
+    player::{
+        fall::FallTracker,
+        input::TickInput,
+        jump_profile::ChargeMode,
+        movement::{LastDirection, Stunned},
+        JuiceMeter, Player,
+    },
+    replay::playing_back,
+    run::{Difficulty, RunStats},
+    state::GameState,
+};
+use avian2d::prelude::LinearVelocity;
+use bevy::{ecs::system::SystemParam, prelude::*};
+use bevy_ecs_ldtk::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{fmt, fs, io, path::PathBuf, time::Duration};
+
+/// Bumped whenever [`SaveData`] changes shape.
+pub const SAVE_VERSION: u32 = 3;
+
+/// Oldest save that can still be read. Fields added since then are `#[serde(default)]`,
+/// so older saves are migrated by filling them in. Saves of any other version are
+/// rejected.
+pub const OLDEST_SAVE_VERSION: u32 = 1;
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(LoadedSave::load())
+            .insert_resource(AutosaveTimer(Timer::from_seconds(
+                30.,
+                TimerMode::Repeating,
+            )))
+            .add_systems(
+                Update,
+                (
+                    continue_game,
+                    new_game,
+                    restore_save.run_if(resource_exists::<PendingRestore>),
+                ),
+            )
+            .add_systems(
+                OnEnter(GameState::Paused),
+                save_on_pause.run_if(not(playing_back)),
+            )
+            .add_systems(
+                OnEnter(GameState::Victory),
+                clear_finished_save.run_if(not(playing_back)),
+            )
+            .add_systems(
+                Update,
+                autosave.run_if(in_state(GameState::Playing).and_then(not(playing_back))),
+            )
+            .add_systems(
+                Last,
+                save_on_exit.run_if(
+                    in_state(GameState::Playing)
+                        .or_else(in_state(GameState::Paused))
+                        .and_then(not(playing_back)),
+                ),
+            );
+    }
+}
+
+/// Everything needed to resume a climb.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SaveData {
+    pub version: u32,
+    pub level_iid: String,
+    /// Player translation relative to the LDtk world.
+    pub translation: [f32; 3],
+    pub direction: f32,
+    pub time: Duration,
+    pub jumps: u32,
+    pub falls: u32,
+    /// Added in version 2.
+    #[serde(default)]
+    pub charge_mode: ChargeMode,
+    /// Added in version 3.
+    #[serde(default)]
+    pub difficulty: Difficulty,
+    /// Added in version 3.
+    #[serde(default)]
+    pub respawn: Option<Respawn>,
+}
+
+/// Only the version, so that saves of another shape can be told apart from corrupt ones.
+#[derive(Deserialize)]
+struct SaveHeader {
+    version: u32,
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    NoDataDir,
+    Io(io::Error),
+    Corrupt(ron::error::SpannedError),
+    Serialize(ron::Error),
+    Version { found: u32, expected: u32 },
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoDataDir => write!(f, "no user data directory available"),
+            Self::Io(err) => write!(f, "{err}"),
+            Self::Corrupt(err) => write!(f, "file is corrupt: {err}"),
+            Self::Serialize(err) => write!(f, "could not serialize: {err}"),
+            Self::Version { found, expected } => write!(
+                f,
+                "file version {found} is not supported (expected {expected})"
+            ),
+        }
+    }
+}
+
+impl From<io::Error> for SaveError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl SaveData {
+    pub fn path() -> Result<PathBuf, SaveError> {
+        dirs::data_dir()
+            .map(|dir| dir.join("jump-wiz").join("save.ron"))
+            .ok_or(SaveError::NoDataDir)
+    }
+
+    /// Reads the save file, returning `None` if there isn't one yet.
+    pub fn read() -> Result<Option<Self>, SaveError> {
+        let contents = match fs::read_to_string(Self::path()?) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+
+        let header: SaveHeader = ron::from_str(&contents).map_err(SaveError::Corrupt)?;
+        if !(OLDEST_SAVE_VERSION..=SAVE_VERSION).contains(&header.version) {
+            return Err(SaveError::Version {
+                found: header.version,
+                expected: SAVE_VERSION,
+            });
+        }
+
+        let mut save: Self = ron::from_str(&contents).map_err(SaveError::Corrupt)?;
+        if save.version != SAVE_VERSION {
+            info!(
+                "migrated save from version {} to {SAVE_VERSION}",
+                save.version
+            );
+            save.version = SAVE_VERSION;
+        }
+
+        Ok(Some(save))
+    }
+
+    pub fn write(&self) -> Result<(), SaveError> {
+        let path = Self::path()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(SaveError::Serialize)?;
+
+        Ok(fs::write(path, contents)?)
+    }
+
+    pub fn clear() -> Result<(), SaveError> {
+        match fs::remove_file(Self::path()?) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The save found on launch, or why it couldn't be used.
+#[derive(Resource, Debug, Default)]
+pub struct LoadedSave {
+    pub save: Option<SaveData>,
+    pub problem: Option<String>,
+}
+
+impl LoadedSave {
+    fn load() -> Self {
+        match SaveData::read() {
+            Ok(save) => Self {
+                save,
+                problem: None,
+            },
+            Err(err) => {
+                error!("failed to load save: {err}");
+                Self {
+                    save: None,
+                    problem: Some(err.to_string()),
+                }
+            }
+        }
+    }
+}
+
+/// A save waiting for the player to exist before it is applied.
+#[derive(Resource)]
+pub(crate) struct PendingRestore(pub SaveData);
+
+#[derive(Resource, Deref, DerefMut)]
+struct AutosaveTimer(Timer);
+
+/// The state a [`SaveData`] is captured from.
+#[derive(SystemParam)]
+pub(crate) struct SaveSource<'w, 's> {
+    player: Query<'w, 's, (&'static Transform, &'static LastDirection), With<Player>>,
+    level_selection: Res<'w, LevelSelection>,
+    projects: Query<'w, 's, &'static Handle<LdtkProject>>,
+    project_assets: Res<'w, Assets<LdtkProject>>,
+    stats: Res<'w, RunStats>,
+    charge_mode: Res<'w, ChargeMode>,
+    difficulty: Res<'w, Difficulty>,
+    respawn: Res<'w, RespawnPoint>,
+}
+
+impl SaveSource<'_, '_> {
+    pub fn snapshot(&self) -> Option<SaveData> {
+        let (transform, direction) = self.player.get_single().ok()?;
+        let project = self
+            .projects
+            .get_single()
+            .ok()
+            .and_then(|handle| self.project_assets.get(handle));
+        let level_iid = selected_level_iid(project, &self.level_selection)?;
+
+        Some(SaveData {
+            version: SAVE_VERSION,
+            level_iid,
+            translation: transform.translation.to_array(),
+            direction: direction.0,
+            time: self.stats.time,
+            jumps: self.stats.jumps,
+            falls: self.stats.falls,
+            charge_mode: *self.charge_mode,
+            difficulty: *self.difficulty,
+            respawn: self.respawn.0.clone(),
+        })
+    }
+
+    fn save(&self) {
+        let Some(save) = self.snapshot() else {
+            return;
+        };
+
+        if let Err(err) = save.write() {
+            error!("failed to write save: {err}");
+        }
+    }
+}
+
+fn save_on_pause(source: SaveSource) {
+    source.save();
+}
+
+fn autosave(time: Res<Time>, mut timer: ResMut<AutosaveTimer>, source: SaveSource) {
+    if timer.tick(time.delta()).just_finished() {
+        source.save();
+    }
+}
+
+fn save_on_exit(mut exit: EventReader<AppExit>, source: SaveSource) {
+    if exit.read().count() > 0 {
+        source.save();
+    }
+}
+
+fn continue_game(
+    mut commands: Commands,
+    mut events: EventReader<ContinueGame>,
+    mut loaded: ResMut<LoadedSave>,
+) {
+    if events.read().count() == 0 {
+        return;
+    }
+
+    if let Some(save) = loaded.save.take() {
+        commands.insert_resource(PendingRestore(save));
+    }
+}
+
+fn new_game(
+    mut events: EventReader<NewGame>,
+    mut loaded: ResMut<LoadedSave>,
+    mut stats: ResMut<RunStats>,
+) {
+    if events.read().count() == 0 {
+        return;
+    }
+
+    if let Err(err) = SaveData::clear() {
+        error!("failed to clear save: {err}");
+    }
+
+    *loaded = LoadedSave::default();
+    *stats = RunStats::default();
+}
+
+/// A finished climb can't be continued, so it leaves no save behind.
+fn clear_finished_save(mut loaded: ResMut<LoadedSave>) {
+    if let Err(err) = SaveData::clear() {
+        error!("failed to clear save: {err}");
+    }
+
+    *loaded = LoadedSave::default();
+}
+
+fn restore_save(
+    mut commands: Commands,
+    pending: Res<PendingRestore>,
+    mut player: Query<
+        (
+            Entity,
+            &mut Transform,
+            &mut LastDirection,
+            &mut LinearVelocity,
+            &mut JuiceMeter,
+            &mut FallTracker,
+            &mut TickInput,
+        ),
+        With<Player>,
+    >,
+    mut level_selection: ResMut<LevelSelection>,
+    mut stats: ResMut<RunStats>,
+    mut charge_mode: ResMut<ChargeMode>,
+    mut difficulty: ResMut<Difficulty>,
+    mut respawn: ResMut<RespawnPoint>,
+    fixed_time: Res<Time<Fixed>>,
+) {
+    // The player is worldly, so it keeps existing once the saved level replaces the
+    // one it was spawned in.
+    let Ok((entity, mut transform, mut direction, mut velocity, mut juice, mut fall, mut input)) =
+        player.get_single_mut()
+    else {
+        return;
+    };
+
+    // Everything a save doesn't keep is reset, so a replay starting from it plays out
+    // the same way it was recorded.
+    let save = &pending.0;
+    transform.translation = Vec3::from_array(save.translation);
+    direction.0 = save.direction;
+    *velocity = LinearVelocity::ZERO;
+    *juice = JuiceMeter::Idle;
+    *fall = FallTracker::default();
+    *input = TickInput::default();
+    commands.entity(entity).remove::<Stunned>();
+    *level_selection = LevelSelection::iid(save.level_iid.clone());
+    *stats = RunStats {
+        time: save.time,
+        ticks: (save.time.as_nanos() / fixed_time.timestep().as_nanos()) as u32,
+        jumps: save.jumps,
+        falls: save.falls,
+    };
+    *charge_mode = save.charge_mode;
+    *difficulty = save.difficulty;
+    respawn.0 = save.respawn.clone();
+
+    commands.remove_resource::<PendingRestore>();
+}
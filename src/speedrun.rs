@@ -0,0 +1,440 @@
+use crate::{
+    map::transition::LevelTransition,
+    menu::{ContinueGame, NewGame},
+    player::{input::TickInput, Player},
+    replay::playing_back,
+    run::format_duration,
+    save::{SaveData, SaveError},
+    state::GameState,
+};
+use bevy::{prelude::*, utils::HashMap};
+use bevy_ecs_ldtk::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{fmt::Write, fs, io, path::PathBuf, time::Duration};
+
+/// Bumped whenever [`SplitsRecord`] changes shape.
+pub const SPLITS_VERSION: u32 = 1;
+
+/// Times the climb from the first input, splitting on the first visit of every level.
+pub struct SpeedrunPlugin;
+
+impl Plugin for SpeedrunPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SplitsRecord::load())
+            .init_resource::<Speedrun>()
+            .add_systems(Startup, spawn_splits_overlay)
+            .add_systems(Update, (reset_speedrun, toggle_splits_overlay))
+            .add_systems(
+                Update,
+                (start_timer, tick_timer, split_on_new_level)
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(
+                OnEnter(GameState::Victory),
+                finish_speedrun.run_if(not(playing_back)),
+            )
+            .add_systems(PostUpdate, update_splits_overlay);
+    }
+}
+
+/// The split recorded when reaching the goal.
+const GOAL_SPLIT: &str = "Summit";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Split {
+    /// The LDtk level iid, or [`GOAL_SPLIT`] for the last split.
+    pub key: String,
+    pub name: String,
+    /// Time since the run started.
+    pub time: Duration,
+}
+
+/// The personal best and the best time ever seen for each segment.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SplitsRecord {
+    pub version: u32,
+    pub attempts: u32,
+    pub personal_best: Vec<Split>,
+    /// Keyed like [`Split::key`].
+    pub best_segments: HashMap<String, Duration>,
+}
+
+impl SplitsRecord {
+    pub fn path() -> Result<PathBuf, SaveError> {
+        SaveData::path().map(|path| path.with_file_name("splits.ron"))
+    }
+
+    fn load() -> Self {
+        match Self::read() {
+            Ok(record) => record,
+            Err(err) => {
+                error!("failed to load splits: {err}");
+                Self::default()
+            }
+        }
+    }
+
+    fn read() -> Result<Self, SaveError> {
+        let contents = match fs::read_to_string(Self::path()?) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                return Ok(Self {
+                    version: SPLITS_VERSION,
+                    ..Default::default()
+                })
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        let record: Self = ron::from_str(&contents).map_err(SaveError::Corrupt)?;
+        if record.version != SPLITS_VERSION {
+            return Err(SaveError::Version {
+                found: record.version,
+                expected: SPLITS_VERSION,
+            });
+        }
+
+        Ok(record)
+    }
+
+    fn write(&self) -> Result<(), SaveError> {
+        let path = Self::path()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(SaveError::Serialize)?;
+
+        Ok(fs::write(path, contents)?)
+    }
+
+    fn personal_best_time(&self) -> Option<Duration> {
+        self.personal_best
+            .last()
+            .filter(|split| split.key == GOAL_SPLIT)
+            .map(|split| split.time)
+    }
+
+    fn personal_best_split(&self, key: &str) -> Option<Duration> {
+        self.personal_best
+            .iter()
+            .find(|split| split.key == key)
+            .map(|split| split.time)
+    }
+
+    /// Writes the personal best as a LiveSplit splits file.
+    pub fn export_lss(&self) -> Result<PathBuf, SaveError> {
+        let path = SaveData::path()?.with_file_name("splits.lss");
+
+        let mut lss = String::new();
+        let _ = writeln!(lss, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+        let _ = writeln!(lss, r#"<Run version="1.7.0">"#);
+        let _ = writeln!(lss, "  <GameIcon />");
+        let _ = writeln!(lss, "  <GameName>jump wiz</GameName>");
+        let _ = writeln!(lss, "  <CategoryName>Any%</CategoryName>");
+        let _ = writeln!(lss, "  <Offset>00:00:00</Offset>");
+        let _ = writeln!(lss, "  <AttemptCount>{}</AttemptCount>", self.attempts);
+        let _ = writeln!(lss, "  <AttemptHistory />");
+        let _ = writeln!(lss, "  <Segments>");
+        for split in &self.personal_best {
+            let _ = writeln!(lss, "    <Segment>");
+            let _ = writeln!(lss, "      <Name>{}</Name>", escape_xml(&split.name));
+            let _ = writeln!(lss, "      <Icon />");
+            let _ = writeln!(lss, "      <SplitTimes>");
+            let _ = writeln!(lss, r#"        <SplitTime name="Personal Best">"#);
+            let _ = writeln!(
+                lss,
+                "          <RealTime>{}</RealTime>",
+                lss_time(split.time)
+            );
+            let _ = writeln!(lss, "        </SplitTime>");
+            let _ = writeln!(lss, "      </SplitTimes>");
+            match self.best_segments.get(&split.key) {
+                Some(best) => {
+                    let _ = writeln!(lss, "      <BestSegmentTime>");
+                    let _ = writeln!(lss, "        <RealTime>{}</RealTime>", lss_time(*best));
+                    let _ = writeln!(lss, "      </BestSegmentTime>");
+                }
+                None => {
+                    let _ = writeln!(lss, "      <BestSegmentTime />");
+                }
+            }
+            let _ = writeln!(lss, "      <SegmentHistory />");
+            let _ = writeln!(lss, "    </Segment>");
+        }
+        let _ = writeln!(lss, "  </Segments>");
+        let _ = writeln!(lss, "  <AutoSplitterSettings />");
+        let _ = writeln!(lss, "</Run>");
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, lss)?;
+
+        Ok(path)
+    }
+}
+
+/// LiveSplit's `hh:mm:ss.fffffff`.
+fn lss_time(time: Duration) -> String {
+    let secs = time.as_secs();
+    format!(
+        "{:02}:{:02}:{:02}.{:07}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        time.subsec_nanos() / 100
+    )
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// The run being timed. Only runs begun with a new game are compared to the personal
+/// best, a resumed run is missing its start.
+#[derive(Resource, Debug, Default)]
+pub struct Speedrun {
+    started: bool,
+    /// Time since the first input.
+    pub time: Duration,
+    splits: Vec<Split>,
+    eligible: bool,
+}
+
+fn reset_speedrun(
+    mut new_game: EventReader<NewGame>,
+    mut continue_game: EventReader<ContinueGame>,
+    mut speedrun: ResMut<Speedrun>,
+) {
+    let eligible = match (new_game.read().count(), continue_game.read().count()) {
+        (0, 0) => return,
+        (_, 0) => true,
+        _ => false,
+    };
+
+    *speedrun = Speedrun {
+        eligible,
+        ..Default::default()
+    };
+}
+
+fn start_timer(
+    mut speedrun: ResMut<Speedrun>,
+    mut record: ResMut<SplitsRecord>,
+    player: Query<&TickInput, With<Player>>,
+) {
+    if speedrun.started {
+        return;
+    }
+
+    let Ok(input) = player.get_single() else {
+        return;
+    };
+
+    if input.movement != 0. || input.jump {
+        speedrun.started = true;
+        if speedrun.eligible {
+            record.attempts += 1;
+        }
+    }
+}
+
+fn tick_timer(mut speedrun: ResMut<Speedrun>, time: Res<Time>) {
+    if speedrun.started {
+        speedrun.time += time.delta();
+    }
+}
+
+fn split_on_new_level(
+    mut speedrun: ResMut<Speedrun>,
+    mut transitions: EventReader<LevelTransition>,
+    projects: Query<&Handle<LdtkProject>>,
+    project_assets: Res<Assets<LdtkProject>>,
+) {
+    let project = projects
+        .get_single()
+        .ok()
+        .and_then(|handle| project_assets.get(handle));
+
+    for transition in transitions.read() {
+        let key = transition.to.as_str();
+        if !speedrun.started || speedrun.splits.iter().any(|split| split.key == key) {
+            continue;
+        }
+
+        let name = project
+            .and_then(|project| project.iter_raw_levels().find(|level| level.iid == key))
+            .map_or_else(|| key.to_owned(), |level| level.identifier.clone());
+
+        let time = speedrun.time;
+        speedrun.splits.push(Split {
+            key: key.to_owned(),
+            name,
+            time,
+        });
+    }
+}
+
+fn finish_speedrun(mut speedrun: ResMut<Speedrun>, mut record: ResMut<SplitsRecord>) {
+    if !speedrun.started || !speedrun.eligible {
+        return;
+    }
+
+    let time = speedrun.time;
+    speedrun.splits.push(Split {
+        key: GOAL_SPLIT.to_owned(),
+        name: GOAL_SPLIT.to_owned(),
+        time,
+    });
+    speedrun.eligible = false;
+
+    let mut previous = Duration::ZERO;
+    for split in &speedrun.splits {
+        let segment = split.time.saturating_sub(previous);
+        previous = split.time;
+
+        record
+            .best_segments
+            .entry(split.key.clone())
+            .and_modify(|best| *best = (*best).min(segment))
+            .or_insert(segment);
+    }
+
+    if record.personal_best_time().is_none_or(|best| time < best) {
+        record.personal_best = speedrun.splits.clone();
+    }
+
+    record.version = SPLITS_VERSION;
+    if let Err(err) = record.write() {
+        error!("failed to write splits: {err}");
+    }
+
+    match record.export_lss() {
+        Ok(path) => info!("exported splits to {}", path.display()),
+        Err(err) => error!("failed to export splits: {err}"),
+    }
+}
+
+#[derive(Component)]
+struct SplitsOverlay {
+    expanded: bool,
+}
+
+fn spawn_splits_overlay(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::default().with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(12.),
+            right: Val::Px(12.),
+            ..Default::default()
+        }),
+        SplitsOverlay { expanded: true },
+    ));
+}
+
+/// Tab collapses the overlay down to the timer.
+fn toggle_splits_overlay(keys: Res<ButtonInput<KeyCode>>, mut overlay: Query<&mut SplitsOverlay>) {
+    if keys.just_pressed(KeyCode::Tab) {
+        for mut overlay in &mut overlay {
+            overlay.expanded = !overlay.expanded;
+        }
+    }
+}
+
+const SPLIT_AHEAD: Color = Color::srgb(0.3, 0.85, 0.4);
+const SPLIT_BEHIND: Color = Color::srgb(0.9, 0.25, 0.25);
+const SPLIT_GOLD: Color = Color::srgb(1., 0.8, 0.2);
+
+/// Updates the timer every frame, and the splits below it only when there are new ones
+/// or the overlay was toggled.
+fn update_splits_overlay(
+    mut overlay: Query<(&SplitsOverlay, &mut Text, &mut Visibility)>,
+    speedrun: Res<Speedrun>,
+    record: Res<SplitsRecord>,
+    state: Res<State<GameState>>,
+    mut shown_layout: Local<Option<(usize, bool, bool)>>,
+) {
+    let Ok((overlay, mut text, mut visibility)) = overlay.get_single_mut() else {
+        return;
+    };
+
+    let shown = matches!(state.get(), GameState::Playing | GameState::Paused);
+    visibility.set_if_neq(if shown {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    });
+    if !shown {
+        return;
+    }
+
+    let timer = format_duration(speedrun.time);
+    let layout = (speedrun.splits.len(), overlay.expanded, speedrun.eligible);
+    if *shown_layout == Some(layout) && !record.is_changed() {
+        if let Some(section) = text.sections.first_mut() {
+            section.value = timer;
+        }
+        return;
+    }
+    *shown_layout = Some(layout);
+
+    let style = TextStyle {
+        font_size: 24.,
+        color: Color::WHITE,
+        ..Default::default()
+    };
+
+    let mut sections = vec![TextSection::new(
+        timer,
+        TextStyle {
+            font_size: 32.,
+            ..style.clone()
+        },
+    )];
+
+    if overlay.expanded {
+        let mut previous = Duration::ZERO;
+        for split in &speedrun.splits {
+            let segment = split.time.saturating_sub(previous);
+            previous = split.time;
+
+            sections.push(TextSection::new(
+                format!("\n{}  {}", split.name, format_duration(split.time)),
+                style.clone(),
+            ));
+
+            if !speedrun.eligible {
+                continue;
+            }
+
+            let Some(best) = record.personal_best_split(&split.key) else {
+                continue;
+            };
+
+            let is_gold = record
+                .best_segments
+                .get(&split.key)
+                .is_none_or(|best_segment| segment < *best_segment);
+            let (sign, delta, color) = if split.time <= best {
+                ("-", best - split.time, SPLIT_AHEAD)
+            } else {
+                ("+", split.time - best, SPLIT_BEHIND)
+            };
+
+            sections.push(TextSection::new(
+                format!("  {sign}{:.2}", delta.as_secs_f32()),
+                TextStyle {
+                    color: if is_gold { SPLIT_GOLD } else { color },
+                    ..style.clone()
+                },
+            ));
+        }
+    }
+
+    text.sections = sections;
+}
@@ -0,0 +1,39 @@
+use crate::map::collision::level_spawned;
+use avian2d::prelude::*;
+use bevy::prelude::*;
+
+pub struct StatePlugin;
+
+impl Plugin for StatePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_state::<GameState>()
+            .enable_state_scoped_entities::<GameState>()
+            .add_systems(Startup, pause_physics)
+            .add_systems(
+                FixedPreUpdate,
+                (
+                    unpause_physics.run_if(in_state(GameState::Playing).and_then(level_spawned)),
+                    pause_physics.run_if(not(in_state(GameState::Playing).and_then(level_spawned))),
+                ),
+            );
+    }
+}
+
+/// The top level flow of the game. Gameplay systems and physics only run while
+/// [`GameState::Playing`], and hold while the selected level's colliders are spawning.
+#[derive(States, Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameState {
+    #[default]
+    Title,
+    Playing,
+    Paused,
+    Victory,
+}
+
+fn pause_physics(mut time: ResMut<Time<Physics>>) {
+    time.pause();
+}
+
+fn unpause_physics(mut time: ResMut<Time<Physics>>) {
+    time.unpause();
+}